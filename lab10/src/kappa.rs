@@ -0,0 +1,95 @@
+/// C+G% and Kappa IC of one window, `start` 0-based.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowPoint {
+    pub start: usize,
+    pub cg: f64,
+    pub kappa: f64,
+}
+
+/// C+G% and Kappa IC of every `step`-th window of `window` bases.
+///
+/// Kappa IC is the mean over shifts `u = 1..window` of the percentage of
+/// positions where the window matches itself shifted by `u`. Rather than
+/// comparing every shift afresh for each window, the match count of each
+/// shift is carried over as the window slides: moving one base drops the
+/// pair starting at the old first base and adds the pair ending at the new
+/// last one. That is `O(window)` per base instead of `O(window²)` per
+/// window.
+pub fn window_profile(seq: &[u8], window: usize, step: usize) -> Vec<WindowPoint> {
+    let n = seq.len();
+    if window < 2 || n < window {
+        return Vec::new();
+    }
+    let step = step.max(1);
+    let is_cg = |b: u8| (b == b'C' || b == b'G') as usize;
+
+    // `matches[u]` counts `i` in the window with `seq[i] == seq[i + u]`,
+    // both inside it.
+    let mut matches = vec![0usize; window];
+    for (u, m) in matches.iter_mut().enumerate().skip(1) {
+        *m = (0..window - u).filter(|&i| seq[i] == seq[i + u]).count();
+    }
+    let mut cg = seq[..window].iter().map(|&b| is_cg(b)).sum::<usize>();
+
+    let mut out = Vec::new();
+    let mut start = 0;
+    loop {
+        if start % step == 0 {
+            let total: f64 = (1..window)
+                .map(|u| matches[u] as f64 / (window - u) as f64 * 100.0)
+                .sum();
+            out.push(WindowPoint {
+                start,
+                cg: cg as f64 / window as f64 * 100.0,
+                kappa: total / (window - 1) as f64,
+            });
+        }
+        let end = start + window;
+        if end == n {
+            break;
+        }
+        for (u, m) in matches.iter_mut().enumerate().skip(1) {
+            *m -= (seq[start] == seq[start + u]) as usize;
+            *m += (seq[end - u] == seq[end]) as usize;
+        }
+        cg = cg + is_cg(seq[end]) - is_cg(seq[start]);
+        start += 1;
+    }
+    out
+}
+
+/// Where a genome sits in the C+G% / Kappa IC plane: the mean of its
+/// windows and their standard deviations.
+pub struct Signature {
+    pub id: String,
+    pub length: usize,
+    pub windows: usize,
+    pub mean_cg: f64,
+    pub mean_kappa: f64,
+    pub sd_cg: f64,
+    pub sd_kappa: f64,
+}
+
+fn mean_sd(values: impl Iterator<Item = f64> + Clone) -> (f64, f64) {
+    let n = values.clone().count();
+    if n == 0 {
+        return (0.0, 0.0);
+    }
+    let mean = values.clone().sum::<f64>() / n as f64;
+    let var = values.map(|v| (v - mean).powi(2)).sum::<f64>() / n as f64;
+    (mean, var.sqrt())
+}
+
+pub fn signature(id: &str, length: usize, points: &[WindowPoint]) -> Signature {
+    let (mean_cg, sd_cg) = mean_sd(points.iter().map(|p| p.cg));
+    let (mean_kappa, sd_kappa) = mean_sd(points.iter().map(|p| p.kappa));
+    Signature {
+        id: id.to_string(),
+        length,
+        windows: points.len(),
+        mean_cg,
+        mean_kappa,
+        sd_cg,
+        sd_kappa,
+    }
+}
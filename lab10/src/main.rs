@@ -1,6 +1,96 @@
+mod kappa;
+mod skew;
+
+use kappa::{Signature, WindowPoint, signature, window_profile};
 use plotters::prelude::*;
+use skew::{CumulativeSkew, SkewWindow, cumulative_gc_skew, find_dnaa_boxes, window_skews};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+
+/// Records as `(id, sequence)`, the id being the first word of the header
+/// and the sequence upper-cased.
+fn read_fasta(path: &str) -> io::Result<Vec<(String, String)>> {
+    let content = fs::read_to_string(path)?;
+    let mut records: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        if let Some(header) = line.strip_prefix('>') {
+            let id = header.split_whitespace().next().unwrap_or("").to_string();
+            records.push((id, String::new()));
+        } else {
+            if records.is_empty() {
+                records.push(("seq".to_string(), String::new()));
+            }
+            if let Some((_, seq)) = records.last_mut() {
+                seq.push_str(&line.trim().to_ascii_uppercase());
+            }
+        }
+    }
+    Ok(records)
+}
+
+fn arg_value(args: &[String], name: &str) -> Option<String> {
+    let eq = format!("{}=", name);
+    let mut it = args.iter().skip(1);
+    while let Some(tok) = it.next() {
+        if tok == name {
+            return it.next().cloned();
+        } else if let Some(v) = tok.strip_prefix(&eq) {
+            return Some(v.to_string());
+        }
+    }
+    None
+}
+
+fn arg_number<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> io::Result<T> {
+    match arg_value(args, name) {
+        Some(v) => v.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} expects a number", name),
+            )
+        }),
+        None => Ok(default),
+    }
+}
+
+/// Arguments that are not flags or flag values.
+fn positionals(args: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut it = args.iter().skip(1);
+    while let Some(tok) = it.next() {
+        if tok.starts_with("--") {
+            if !tok.contains('=') {
+                it.next();
+            }
+        } else {
+            out.push(tok.clone());
+        }
+    }
+    out
+}
+
+/// `base` as is for a single record, otherwise with the record id before
+/// the extension.
+fn record_path(base: &str, id: &str, many: bool) -> String {
+    if !many {
+        return base.to_string();
+    }
+    match base.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}_{}.{}", stem, id, ext),
+        None => format!("{}_{}", base, id),
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).is_some_and(|a| a == "skew") {
+        return run_skew(&args[1..]);
+    }
+    if args.get(1).is_some_and(|a| a == "kappa") {
+        return run_kappa(&args[1..]);
+    }
+
     let s = "CGGACTGATCTATCTAAAAAAAAAAAAAAAAAAAAAAAAAAACGTAGCATCTATCGATCTATCTAGCGATCTATCTACTACG";
     let window_size = 30;
 
@@ -9,129 +99,428 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Window Size: {}", window_size);
     println!("---");
 
-    let mut cg_values = Vec::new();
-    let mut kappa_values = Vec::new();
-    let mut points = Vec::new();
-
-    let windows: Vec<&str> = s
-        .as_bytes()
-        .windows(window_size)
-        .map(|w| std::str::from_utf8(w).unwrap())
-        .collect();
-
-    for (i, window) in windows.iter().enumerate() {
-        let cg = calc_cg_content(window);
-        
-        let kappa = calc_kappa_ic(window);
-
-        cg_values.push(cg);
-        kappa_values.push(kappa);
-        points.push((cg, kappa));
-
-        if i == 0 {
-             println!("Window 0: {}", window);
-             println!("CG%: {:.2}", cg);
-             println!("Kappa IC: {:.2}", kappa);
-        }
+    let profile = window_profile(s.as_bytes(), window_size, 1);
+    if let Some(first) = profile.first() {
+        println!("Window 0: {}", &s[first.start..first.start + window_size]);
+        println!("CG%: {:.2}", first.cg);
+        println!("Kappa IC: {:.2}", first.kappa);
     }
-
-    let avg_cg: f64 = cg_values.iter().sum::<f64>() / cg_values.len() as f64;
-    let avg_kappa: f64 = kappa_values.iter().sum::<f64>() / kappa_values.len() as f64;
+    let sig = signature("sequence", s.len(), &profile);
 
     println!("---");
-    println!("Calculated Average CG%:     {:.2}", avg_cg);
-    println!("Calculated Average Kappa IC: {:.2}", avg_kappa);
+    println!("Calculated Average CG%:     {:.2}", sig.mean_cg);
+    println!("Calculated Average Kappa IC: {:.2}", sig.mean_kappa);
 
-    plot_pattern(&points, "pattern_chart.png")?;
-    
-    plot_center(avg_cg, avg_kappa, "center_chart.png")?;
+    plot_pattern(
+        &[(sig.id.as_str(), profile.as_slice())],
+        "pattern_chart.png",
+    )?;
+
+    plot_centers(&[sig], "center_chart.png")?;
 
     Ok(())
 }
 
-fn calc_cg_content(window: &str) -> f64 {
-    let mut count = 0;
-    for c in window.chars() {
-        if c == 'C' || c == 'G' {
-            count += 1;
-        }
+/// `kappa genomes.fasta...`: C+G% and Kappa IC per window of every record
+/// in every file, each record taken as a genome, and its center of weight.
+fn run_kappa(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let paths = positionals(args);
+    if paths.is_empty() {
+        println!(
+            "Usage. cargo run kappa <fasta>... [--window 30] [--step 10] \
+             [--pattern pattern_chart.png] [--plot center_chart.png] [--tsv signatures.tsv]"
+        );
+        return Ok(());
     }
-    (count as f64 / window.len() as f64) * 100.0
-}
-
-fn calc_kappa_ic(window: &str) -> f64 {
-    let n = window.len();
-    let chars: Vec<char> = window.chars().collect();
-    let mut total_probability = 0.0;
-
-    let max_u = n - 1; 
+    let window = arg_number(args, "--window", 30)?;
+    let step = arg_number(args, "--step", 10)?;
+    if window < 2 || step == 0 {
+        return Err("--window must be at least 2 and --step positive".into());
+    }
+    let pattern = arg_value(args, "--pattern").unwrap_or_else(|| "pattern_chart.png".to_string());
+    let plot = arg_value(args, "--plot").unwrap_or_else(|| "center_chart.png".to_string());
 
-    for u in 1..=max_u {
-        let mut matches = 0;
-        let suffix_len = n - u;
-        
-        for i in 0..suffix_len {
-            if chars[i] == chars[i + u] {
-                matches += 1;
+    println!(
+        "{:<24} {:>10} {:>9} {:>8} {:>7} {:>9} {:>7}",
+        "Genome", "Length", "Windows", "CG%", "SD", "Kappa IC", "SD"
+    );
+    let mut signatures = Vec::new();
+    let mut samples = Vec::new();
+    for path in &paths {
+        for (id, seq) in read_fasta(path)? {
+            let profile = window_profile(seq.as_bytes(), window, step);
+            if profile.is_empty() {
+                println!("{}: shorter than the window, skipped", id);
+                continue;
             }
+            let sig = signature(&id, seq.len(), &profile);
+            println!(
+                "{:<24} {:>10} {:>9} {:>8.2} {:>7.2} {:>9.2} {:>7.2}",
+                sig.id,
+                sig.length,
+                sig.windows,
+                sig.mean_cg,
+                sig.sd_cg,
+                sig.mean_kappa,
+                sig.sd_kappa
+            );
+            // Enough windows to show the shape of the cloud.
+            let every = profile.len().div_ceil(PATTERN_POINTS);
+            samples.push(profile.into_iter().step_by(every).collect::<Vec<_>>());
+            signatures.push(sig);
         }
+    }
 
-        if suffix_len > 0 {
-            total_probability += (matches as f64 / suffix_len as f64) * 100.0;
+    if let Some(out) = arg_value(args, "--tsv") {
+        let mut file = fs::File::create(&out)?;
+        writeln!(
+            file,
+            "id\tlength\twindows\tmean_cg\tsd_cg\tmean_kappa_ic\tsd_kappa_ic"
+        )?;
+        for sig in &signatures {
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{:.4}",
+                sig.id,
+                sig.length,
+                sig.windows,
+                sig.mean_cg,
+                sig.sd_cg,
+                sig.mean_kappa,
+                sig.sd_kappa
+            )?;
         }
+        println!("Signatures written to {}", out);
     }
-
-    total_probability / max_u as f64
+    let series: Vec<(&str, &[WindowPoint])> = signatures
+        .iter()
+        .zip(&samples)
+        .map(|(sig, points)| (sig.id.as_str(), points.as_slice()))
+        .collect();
+    plot_pattern(&series, &pattern)?;
+    println!("Pattern chart written to {}", pattern);
+    plot_centers(&signatures, &plot)?;
+    println!("Center of weight chart written to {}", plot);
+    Ok(())
 }
 
-fn plot_pattern(points: &[(f64, f64)], filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Most windows per genome drawn on the pattern chart.
+const PATTERN_POINTS: usize = 2000;
+
+fn plot_pattern(
+    series: &[(&str, &[WindowPoint])],
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
     let root = BitMapBackend::new(filename, (640, 480)).into_drawing_area();
     root.fill(&WHITE)?;
 
     let mut chart = ChartBuilder::on(&root)
-        .caption("DNA Pattern (C+G% vs Kappa IC)", ("sans-serif", 30).into_font())
+        .caption(
+            "DNA Pattern (C+G% vs Kappa IC)",
+            ("sans-serif", 30).into_font(),
+        )
         .margin(10)
         .x_label_area_size(30)
         .y_label_area_size(30)
         .build_cartesian_2d(0f64..100f64, 0f64..100f64)?;
 
-    chart.configure_mesh()
+    chart
+        .configure_mesh()
         .x_desc("C+G %")
         .y_desc("Kappa IC")
         .draw()?;
 
-    chart.draw_series(
-        points.iter().map(|(x, y)| Circle::new((*x, *y), 3, BLUE.filled()))
-    )?;
+    for (i, (id, points)) in series.iter().enumerate() {
+        let color: RGBAColor = if series.len() == 1 {
+            BLUE.into()
+        } else {
+            Palette99::pick(i).to_rgba()
+        };
+        chart
+            .draw_series(
+                points
+                    .iter()
+                    .map(move |p| Circle::new((p.cg, p.kappa), 3, color.filled())),
+            )?
+            .label(*id)
+            .legend(move |(x, y)| Circle::new((x + 10, y), 3, color.filled()));
+    }
+    if series.len() > 1 {
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+    }
 
     Ok(())
 }
 
-fn plot_center(avg_cg: f64, avg_kappa: f64, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Each genome's center of weight, with bars one standard deviation long
+/// along both axes, on axes fitted to the genomes shown.
+fn plot_centers(
+    signatures: &[Signature],
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
     let root = BitMapBackend::new(filename, (640, 480)).into_drawing_area();
     root.fill(&WHITE)?;
 
+    let range = |lo: f64, hi: f64| (lo - 5.0).max(0.0)..(hi + 5.0).min(100.0);
+    let x = range(
+        signatures
+            .iter()
+            .map(|s| s.mean_cg - s.sd_cg)
+            .fold(100.0, f64::min),
+        signatures
+            .iter()
+            .map(|s| s.mean_cg + s.sd_cg)
+            .fold(0.0, f64::max),
+    );
+    let y = range(
+        signatures
+            .iter()
+            .map(|s| s.mean_kappa - s.sd_kappa)
+            .fold(100.0, f64::min),
+        signatures
+            .iter()
+            .map(|s| s.mean_kappa + s.sd_kappa)
+            .fold(0.0, f64::max),
+    );
+    let mid_cg = (x.start + x.end) / 2.0;
     let mut chart = ChartBuilder::on(&root)
         .caption("Center of Weight", ("sans-serif", 30).into_font())
         .margin(10)
         .x_label_area_size(30)
-        .y_label_area_size(30)
-        .build_cartesian_2d(0f64..100f64, 0f64..100f64)?;
+        .y_label_area_size(45)
+        .build_cartesian_2d(x, y)?;
 
-    chart.configure_mesh()
+    chart
+        .configure_mesh()
         .x_desc("Average C+G %")
         .y_desc("Average Kappa IC")
         .draw()?;
 
-    chart.draw_series(std::iter::once(
-        EmptyElement::at((avg_cg, avg_kappa))
-            + Circle::new((0, 0), 6, RED.filled())
-            + Text::new(
-                format!("({:.2}, {:.2})", avg_cg, avg_kappa),
-                (10, 0),
-                ("sans-serif", 15).into_font(),
-            ),
-    ))?;
+    for (i, sig) in signatures.iter().enumerate() {
+        let color: RGBAColor = if signatures.len() == 1 {
+            RED.into()
+        } else {
+            Palette99::pick(i).to_rgba()
+        };
+        let (cx, cy) = (sig.mean_cg, sig.mean_kappa);
+        chart.draw_series(std::iter::once(ErrorBar::new_horizontal(
+            cy,
+            cx - sig.sd_cg,
+            cx,
+            cx + sig.sd_cg,
+            color,
+            6,
+        )))?;
+        chart.draw_series(std::iter::once(ErrorBar::new_vertical(
+            cx,
+            cy - sig.sd_kappa,
+            cy,
+            cy + sig.sd_kappa,
+            color,
+            6,
+        )))?;
+        let label = if signatures.len() == 1 {
+            format!("({:.2}, {:.2})", cx, cy)
+        } else {
+            format!("{} ({:.2}, {:.2})", sig.id, cx, cy)
+        };
+        chart.draw_series(std::iter::once(
+            EmptyElement::at((cx, cy))
+                + Circle::new((0, 0), 6, color.filled())
+                // Labels in the right half go left of their point.
+                + Text::new(
+                    label.clone(),
+                    (if cx > mid_cg { -7 * label.len() as i32 - 10 } else { 10 }, 0),
+                    ("sans-serif", 15).into_font(),
+                ),
+        ))?;
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// `skew genome.fasta`: GC and AT skew per window and the cumulative GC
+/// skew of every record, the origin and terminus of replication at its
+/// minimum and maximum, and DnaA boxes around the origin.
+fn run_skew(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = positionals(args).into_iter().next() else {
+        println!(
+            "Usage. cargo run skew <path_to_fasta> [--window 10000] [--step 5000] \
+             [--dnaa-range 2000] [--dnaa-mismatches 1] [--plot skew_chart.png] [--tsv skew.tsv]"
+        );
+        return Ok(());
+    };
+    let window: usize = arg_number(args, "--window", 10000)?;
+    let step = arg_number(args, "--step", window / 2)?;
+    let dnaa_range = arg_number(args, "--dnaa-range", 2000)?;
+    let dnaa_mismatches = arg_number(args, "--dnaa-mismatches", 1)?;
+    let plot = arg_value(args, "--plot").unwrap_or_else(|| "skew_chart.png".to_string());
+    if window == 0 || step == 0 {
+        return Err("--window and --step must be positive".into());
+    }
+
+    let records = read_fasta(&path)?;
+    let many = records.len() > 1;
+    let mut tsv = match arg_value(args, "--tsv") {
+        Some(p) => {
+            let mut file = fs::File::create(&p)?;
+            writeln!(file, "id\tstart\tend\tgc_skew\tat_skew")?;
+            Some((file, p))
+        }
+        None => None,
+    };
+
+    for (id, seq) in &records {
+        let s = seq.as_bytes();
+        let windows = window_skews(s, window, step);
+        let cumulative = cumulative_gc_skew(s, step);
+        let n = s.len();
+        println!(
+            "{}: {} bp, {} windows of {} bp",
+            id,
+            n,
+            windows.len(),
+            window.min(n)
+        );
+        println!(
+            "Predicted origin near {} (cumulative GC skew minimum {}), terminus near {} (maximum {})",
+            cumulative.origin + 1,
+            cumulative.min,
+            cumulative.terminus + 1,
+            cumulative.max
+        );
+        if n > 0 {
+            let apart = cumulative.origin.abs_diff(cumulative.terminus);
+            println!(
+                "Origin and terminus are {:.1}% of the sequence apart (50% for equal replichores)",
+                100.0 * apart.min(n - apart) as f64 / n as f64
+            );
+        }
+
+        let boxes = find_dnaa_boxes(s, cumulative.origin, dnaa_range, dnaa_mismatches);
+        let exact = boxes.iter().filter(|b| b.mismatches == 0).count();
+        println!(
+            "{} DnaA boxes within {} bp of the origin ({} exact, up to {} mismatches):",
+            boxes.len(),
+            dnaa_range,
+            exact,
+            dnaa_mismatches
+        );
+        for b in &boxes {
+            println!(
+                "  {} ({}) {} {} mismatches",
+                b.pos + 1,
+                b.strand,
+                b.sequence,
+                b.mismatches
+            );
+        }
+
+        if let Some((file, _)) = tsv.as_mut() {
+            for w in &windows {
+                writeln!(
+                    file,
+                    "{}\t{}\t{}\t{:.5}\t{:.5}",
+                    id, w.start, w.end, w.gc_skew, w.at_skew
+                )?;
+            }
+        }
+        let out = record_path(&plot, id, many);
+        plot_skew(id, &windows, &cumulative, &out)?;
+        println!("Skew chart written to {}", out);
+    }
+    if let Some((_, p)) = tsv {
+        println!("Window skews written to {}", p);
+    }
+    Ok(())
+}
+
+/// Window GC and AT skews above, and the cumulative GC skew with the
+/// predicted origin and terminus below.
+fn plot_skew(
+    id: &str,
+    windows: &[SkewWindow],
+    cumulative: &CumulativeSkew,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let root = BitMapBackend::new(filename, (1000, 800)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let (upper, lower) = root.split_vertically(400);
+    let length = cumulative.points.last().map_or(1, |p| p.0.max(1));
+
+    let bound = windows
+        .iter()
+        .map(|w| w.gc_skew.abs().max(w.at_skew.abs()))
+        .fold(0.01, f64::max);
+    let mut chart = ChartBuilder::on(&upper)
+        .caption(
+            format!("{}: GC and AT skew", id),
+            ("sans-serif", 24).into_font(),
+        )
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0..length, -bound..bound)?;
+    chart
+        .configure_mesh()
+        .x_desc("Position (bp)")
+        .y_desc("Skew")
+        .draw()?;
+    let centre = |w: &SkewWindow| (w.start + w.end) / 2;
+    chart
+        .draw_series(LineSeries::new(
+            windows.iter().map(|w| (centre(w), w.gc_skew)),
+            &BLUE,
+        ))?
+        .label("(G - C) / (G + C)")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+    chart
+        .draw_series(LineSeries::new(
+            windows.iter().map(|w| (centre(w), w.at_skew)),
+            &GREEN,
+        ))?
+        .label("(A - T) / (A + T)")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], GREEN));
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    let (min, max) = (cumulative.min, cumulative.max.max(cumulative.min + 1));
+    let pad = (max - min) / 20 + 1;
+    let mut chart = ChartBuilder::on(&lower)
+        .caption("Cumulative GC skew", ("sans-serif", 24).into_font())
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0..length, min - pad..max + pad)?;
+    chart
+        .configure_mesh()
+        .x_desc("Position (bp)")
+        .y_desc("G - C")
+        .draw()?;
+    chart.draw_series(LineSeries::new(cumulative.points.iter().copied(), &BLACK))?;
+    for (label, pos, value, color) in [
+        ("ori", cumulative.origin, cumulative.min, RED),
+        ("ter", cumulative.terminus, cumulative.max, MAGENTA),
+    ] {
+        chart.draw_series(std::iter::once(
+            EmptyElement::at((pos, value))
+                + Circle::new((0, 0), 5, color.filled())
+                + Text::new(
+                    format!("{} {}", label, pos + 1),
+                    // Labels in the right half go left of their point.
+                    (if pos > length / 2 { -90 } else { 8 }, -12),
+                    ("sans-serif", 15).into_font(),
+                ),
+        ))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+/// Strand composition of one window, `start` 0-based.
+pub struct SkewWindow {
+    pub start: usize,
+    pub end: usize,
+    /// `(G - C) / (G + C)`.
+    pub gc_skew: f64,
+    /// `(A - T) / (A + T)`.
+    pub at_skew: f64,
+}
+
+fn skew(plus: usize, minus: usize) -> f64 {
+    if plus + minus == 0 {
+        0.0
+    } else {
+        (plus as f64 - minus as f64) / (plus + minus) as f64
+    }
+}
+
+/// GC and AT skews in windows of `window` bases every `step` bases; a
+/// sequence shorter than the window is taken as one window.
+pub fn window_skews(seq: &[u8], window: usize, step: usize) -> Vec<SkewWindow> {
+    let window = window.min(seq.len()).max(1);
+    let mut out = Vec::new();
+    let mut start = 0;
+    while start + window <= seq.len() {
+        let mut counts = [0usize; 4];
+        for &b in &seq[start..start + window] {
+            match b {
+                b'A' => counts[0] += 1,
+                b'C' => counts[1] += 1,
+                b'G' => counts[2] += 1,
+                b'T' => counts[3] += 1,
+                _ => {}
+            }
+        }
+        out.push(SkewWindow {
+            start,
+            end: start + window,
+            gc_skew: skew(counts[2], counts[1]),
+            at_skew: skew(counts[0], counts[3]),
+        });
+        start += step.max(1);
+    }
+    out
+}
+
+/// The running sum of `G = +1, C = -1` along the sequence, sampled every
+/// `step` bases for plotting, and the bases (0-based) where it reaches its
+/// minimum and maximum.
+/// The leading strand has an excess of G, so the sum falls up to the origin
+/// of replication and rises up to the terminus.
+pub struct CumulativeSkew {
+    pub points: Vec<(usize, i64)>,
+    pub origin: usize,
+    pub terminus: usize,
+    pub min: i64,
+    pub max: i64,
+}
+
+/// The cumulative GC skew of `seq`, with the predicted origin and terminus.
+pub fn cumulative_gc_skew(seq: &[u8], step: usize) -> CumulativeSkew {
+    let step = step.max(1);
+    let mut sum = 0i64;
+    let mut points = Vec::new();
+    // Taken from the first base on, so an empty prefix is never reported.
+    let (mut min, mut max) = ((0, i64::MAX), (0, i64::MIN));
+    for (i, &b) in seq.iter().enumerate() {
+        match b {
+            b'G' => sum += 1,
+            b'C' => sum -= 1,
+            _ => {}
+        }
+        if sum < min.1 {
+            min = (i, sum);
+        }
+        if sum > max.1 {
+            max = (i, sum);
+        }
+        if (i + 1) % step == 0 || i + 1 == seq.len() {
+            points.push((i + 1, sum));
+        }
+    }
+    if seq.is_empty() {
+        (min.1, max.1) = (0, 0);
+    }
+    CumulativeSkew {
+        points,
+        origin: min.0,
+        terminus: max.0,
+        min: min.1,
+        max: max.1,
+    }
+}
+
+/// DnaA box consensus, `TTATNCACA`, as the most common `TTATCCACA`.
+pub const DNAA_BOX: &[u8; 9] = b"TTATCCACA";
+
+/// A 9-mer within reach of the origin matching [`DNAA_BOX`] on `strand`,
+/// `pos` 0-based on the forward strand.
+pub struct DnaaBox {
+    pub pos: usize,
+    pub strand: char,
+    pub sequence: String,
+    pub mismatches: usize,
+}
+
+fn complement(b: u8) -> u8 {
+    match b {
+        b'A' => b'T',
+        b'T' => b'A',
+        b'C' => b'G',
+        b'G' => b'C',
+        other => other,
+    }
+}
+
+/// 9-mers starting within `range` bases of `origin` with at most
+/// `max_mismatches` mismatches to [`DNAA_BOX`] on either strand, in order
+/// along the sequence from `range` bases before the origin. The sequence is
+/// taken as circular, as bacterial chromosomes are, so the search wraps
+/// around its ends.
+pub fn find_dnaa_boxes(
+    seq: &[u8],
+    origin: usize,
+    range: usize,
+    max_mismatches: usize,
+) -> Vec<DnaaBox> {
+    let n = seq.len();
+    let k = DNAA_BOX.len();
+    if n < k {
+        return Vec::new();
+    }
+    let reach = range.min(n / 2);
+    let mut found = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for offset in 0..=2 * reach {
+        let pos = (origin + n + offset - reach) % n;
+        if !seen.insert(pos) {
+            continue;
+        }
+        let kmer: Vec<u8> = (0..k).map(|t| seq[(pos + t) % n]).collect();
+        let reverse: Vec<u8> = kmer.iter().rev().map(|&b| complement(b)).collect();
+        for (strand, site) in [('+', &kmer), ('-', &reverse)] {
+            let mismatches = site.iter().zip(DNAA_BOX).filter(|(a, b)| a != b).count();
+            if mismatches <= max_mismatches {
+                found.push(DnaaBox {
+                    pos,
+                    strand,
+                    sequence: String::from_utf8_lossy(site).into_owned(),
+                    mismatches,
+                });
+            }
+        }
+    }
+    found
+}
@@ -1,5 +1,62 @@
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
+
 const SEQ: &str = "TACGTGCGCGCGAGCTATCTACTGACTTACGACTAGTGTAGCTGCATCATCGATCGA";
 
+/// Number of independently locked hash tables used by `count_kmers`.
+const SHARDS: usize = 16;
+/// Bases handed to one rayon task at a time (plus k-1 bases of overlap).
+const WINDOW: usize = 1 << 16;
+
+fn arg_threads() -> Option<usize> {
+    let mut it = env::args().skip(1);
+    while let Some(tok) = it.next() {
+        if tok == "--threads" {
+            if let Some(v) = it.next()
+                && let Ok(t) = v.parse()
+            {
+                return Some(t);
+            }
+        } else if let Some(v) = tok.strip_prefix("--threads=")
+            && let Ok(t) = v.parse()
+        {
+            return Some(t);
+        }
+    }
+    None
+}
+
+fn arg_path() -> Option<String> {
+    let mut it = env::args().skip(1);
+    while let Some(tok) = it.next() {
+        if tok == "--threads" {
+            it.next();
+        } else if !tok.starts_with("--") {
+            return Some(tok);
+        }
+    }
+    None
+}
+
+fn read_fasta(path: &str) -> Result<String, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let seq: String = text
+        .lines()
+        .filter(|l| !l.starts_with('>'))
+        .flat_map(|l| l.trim().chars())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if seq.is_empty() {
+        return Err("No sequence found".into());
+    }
+    Ok(seq)
+}
+
 fn generate_all_k(k: usize) -> Vec<String> {
     let mut result = vec![String::new()];
     for _ in 0..k {
@@ -16,40 +73,90 @@ fn generate_all_k(k: usize) -> Vec<String> {
     result
 }
 
-fn count_overlapping(text: &str, pattern: &str) -> usize {
-    if pattern.is_empty() {
-        return 0;
+fn encode(b: u8) -> Option<u64> {
+    match b {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
     }
-    let tb = text.as_bytes();
-    let pb = pattern.as_bytes();
-    let n = tb.len();
-    let m = pb.len();
-    if m > n {
-        return 0;
+}
+
+fn pack(kmer: &str) -> Option<u64> {
+    kmer.bytes()
+        .try_fold(0u64, |acc, b| encode(b).map(|c| (acc << 2) | c))
+}
+
+fn shard_of(kmer: u64) -> usize {
+    let mut h = DefaultHasher::new();
+    kmer.hash(&mut h);
+    (h.finish() as usize) % SHARDS
+}
+
+/// Counts overlapping k-mers (k ≤ 32) of `seq` on the current rayon pool.
+/// Each task counts one window locally and merges it into the shared shards,
+/// so the counts do not depend on the number of threads.
+fn count_kmers(seq: &str, k: usize) -> Vec<HashMap<u64, usize>> {
+    assert!((1..=32).contains(&k), "k must be between 1 and 32");
+    let shards: Vec<Mutex<HashMap<u64, usize>>> =
+        (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect();
+    let bytes = seq.as_bytes();
+    if bytes.len() < k {
+        return shards
+            .into_iter()
+            .map(|m| m.into_inner().unwrap())
+            .collect();
     }
-    let mut count = 0;
-    for i in 0..=(n - m) {
-        let window = &tb[i..i + m];
-        if window == pb {
-            count += 1;
+
+    let mask = if k == 32 {
+        u64::MAX
+    } else {
+        (1u64 << (2 * k)) - 1
+    };
+    let starts: Vec<usize> = (0..=bytes.len() - k).step_by(WINDOW).collect();
+    starts.par_iter().for_each(|&start| {
+        let end = (start + WINDOW + k - 1).min(bytes.len());
+        let mut local: Vec<HashMap<u64, usize>> = vec![HashMap::new(); SHARDS];
+        let mut kmer = 0u64;
+        let mut filled = 0usize;
+        for &b in &bytes[start..end] {
+            match encode(b) {
+                Some(c) => {
+                    kmer = ((kmer << 2) | c) & mask;
+                    filled += 1;
+                    if filled >= k {
+                        *local[shard_of(kmer)].entry(kmer).or_insert(0) += 1;
+                    }
+                }
+                None => filled = 0,
+            }
         }
-    }
-    count
+        for (i, counts) in local.into_iter().enumerate() {
+            let mut table = shards[i].lock().unwrap();
+            for (kmer, c) in counts {
+                *table.entry(kmer).or_insert(0) += c;
+            }
+        }
+    });
+
+    shards
+        .into_iter()
+        .map(|m| m.into_inner().unwrap())
+        .collect()
 }
 
-fn compute_per_k(k: usize) -> (Vec<(String, usize, usize, f64)>, usize) {
-    let n = SEQ.len();
-    let total_windows: usize;
-    if k <= n {
-        total_windows = n - k + 1;
-    } else {
-        total_windows = 0;
-    }
+fn compute_per_k(seq: &str, k: usize) -> (Vec<(String, usize, usize, f64)>, usize) {
+    let n = seq.len();
+    let total_windows = if k <= n { n - k + 1 } else { 0 };
+    let counts = count_kmers(seq, k);
     let mut rows: Vec<(String, usize, usize, f64)> = Vec::new();
     let mut kmers = generate_all_k(k);
     kmers.sort();
     for kmer in kmers {
-        let c = count_overlapping(SEQ, &kmer);
+        let c = pack(&kmer)
+            .and_then(|p| counts[shard_of(p)].get(&p).copied())
+            .unwrap_or(0);
         let pct = if total_windows > 0 {
             (c as f64) * 100.0 / (total_windows as f64)
         } else {
@@ -60,8 +167,8 @@ fn compute_per_k(k: usize) -> (Vec<(String, usize, usize, f64)>, usize) {
     (rows, total_windows)
 }
 
-fn print_k_percentages(k: usize) {
-    let (rows, total) = compute_per_k(k);
+fn print_k_percentages(seq: &str, k: usize) {
+    let (rows, total) = compute_per_k(seq, k);
     println!("combination, count, total, percentage");
     for (kmer, count, _total, pct) in rows {
         println!("{}, {}, {}, {:.3}", kmer, count, total, pct);
@@ -69,9 +176,20 @@ fn print_k_percentages(k: usize) {
     println!();
 }
 
-fn main() {
+fn main() -> Result<(), Box<dyn Error>> {
+    if let Some(t) = arg_threads() {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(t)
+            .build_global()?;
+    }
+    let seq = match arg_path() {
+        Some(path) => read_fasta(&path)?,
+        None => SEQ.to_string(),
+    };
+
     println!("for dinucleotides:");
-    print_k_percentages(2);
+    print_k_percentages(&seq, 2);
     println!("for trinucleotides:");
-    print_k_percentages(3);
+    print_k_percentages(&seq, 3);
+    Ok(())
 }
@@ -1,22 +1,26 @@
 use rand::{Rng, SeedableRng, rngs::StdRng};
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
 use std::env;
 use std::error::Error;
 use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
+use std::time::Instant;
 
 fn arg_k() -> Option<usize> {
     let mut it = env::args().skip(2);
     while let Some(tok) = it.next() {
         if tok == "--k" {
-            if let Some(v) = it.next() {
-                if let Ok(k) = v.parse() {
-                    return Some(k);
-                }
-            }
-        } else if let Some(v) = tok.strip_prefix("--k=") {
-            if let Ok(k) = v.parse() {
+            if let Some(v) = it.next()
+                && let Ok(k) = v.parse()
+            {
                 return Some(k);
             }
+        } else if let Some(v) = tok.strip_prefix("--k=")
+            && let Ok(k) = v.parse()
+        {
+            return Some(k);
         }
     }
     None
@@ -26,15 +30,15 @@ fn arg_seed() -> Option<u64> {
     let mut it = env::args().skip(2);
     while let Some(tok) = it.next() {
         if tok == "--seed" {
-            if let Some(v) = it.next() {
-                if let Ok(s) = v.parse() {
-                    return Some(s);
-                }
-            }
-        } else if let Some(v) = tok.strip_prefix("--seed=") {
-            if let Ok(s) = v.parse() {
+            if let Some(v) = it.next()
+                && let Ok(s) = v.parse()
+            {
                 return Some(s);
             }
+        } else if let Some(v) = tok.strip_prefix("--seed=")
+            && let Ok(s) = v.parse()
+        {
+            return Some(s);
         }
     }
     None
@@ -54,6 +58,70 @@ fn arg_record() -> Option<String> {
     None
 }
 
+fn arg_coverage() -> Option<f64> {
+    let mut it = env::args().skip(2);
+    while let Some(tok) = it.next() {
+        if tok == "--coverage" {
+            if let Some(v) = it.next()
+                && let Ok(c) = v.parse()
+            {
+                return Some(c);
+            }
+        } else if let Some(v) = tok.strip_prefix("--coverage=")
+            && let Ok(c) = v.parse()
+        {
+            return Some(c);
+        }
+    }
+    None
+}
+
+fn arg_threads() -> Option<usize> {
+    let mut it = env::args().skip(2);
+    while let Some(tok) = it.next() {
+        if tok == "--threads" {
+            if let Some(v) = it.next()
+                && let Ok(t) = v.parse()
+            {
+                return Some(t);
+            }
+        } else if let Some(v) = tok.strip_prefix("--threads=")
+            && let Ok(t) = v.parse()
+        {
+            return Some(t);
+        }
+    }
+    None
+}
+
+fn arg_flag(name: &str) -> bool {
+    env::args().skip(2).any(|tok| tok == name)
+}
+
+fn arg_ks() -> Option<Vec<usize>> {
+    let mut it = env::args().skip(2);
+    while let Some(tok) = it.next() {
+        let list = if tok == "--ks" {
+            it.next()
+        } else {
+            tok.strip_prefix("--ks=").map(|v| v.to_string())
+        };
+        if let Some(list) = list {
+            let mut ks: Vec<usize> = list
+                .split(',')
+                .filter_map(|v| v.trim().parse().ok())
+                .filter(|&k| k >= 2)
+                .collect();
+            ks.sort_unstable();
+            ks.dedup();
+            if !ks.is_empty() {
+                return Some(ks);
+            }
+        }
+    }
+    None
+}
+
 fn read_fasta_records(path: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
     let text = fs::read_to_string(path)?;
     let mut records: Vec<(String, String)> = Vec::new();
@@ -65,14 +133,14 @@ fn read_fasta_records(path: &str) -> Result<Vec<(String, String)>, Box<dyn Error
         if line.is_empty() {
             continue;
         }
-        if line.starts_with('>') {
+        if let Some(h) = line.strip_prefix('>') {
             if !header.is_empty() || !seq.is_empty() {
                 if !seq.is_empty() {
                     records.push((header.clone(), seq.clone()));
                 }
                 seq.clear();
             }
-            header = line[1..].to_string();
+            header = h.to_string();
         } else {
             for ch in line.chars() {
                 let up = match ch {
@@ -111,101 +179,246 @@ fn sample_reads(seq: &str, n: usize, len_min: usize, len_max: usize, seed: u64)
     reads
 }
 
-fn assemble_debruijn(reads: &[String], k: usize) -> String {
-    assert!(k >= 2, "k trebuie să fie ≥ 2");
-    let min_read = reads.iter().map(|r| r.len()).min().expect("fără reads");
-    assert!(k <= min_read, "k trebuie ≤ lungimea minimă a read-ului");
+const BASES: [u8; 4] = *b"ACGT";
 
-    let mut node_id: HashMap<String, usize> = HashMap::new();
-    let mut id_node: Vec<String> = Vec::new();
-    let mut next_id = 0usize;
-
-    let mut adj: Vec<Vec<(usize, u8)>> = Vec::new();
-    let mut indeg: Vec<usize> = Vec::new();
-    let mut outdeg: Vec<usize> = Vec::new();
-
-    let intern = |s: &str,
-                  node_id: &mut HashMap<String, usize>,
-                  id_node: &mut Vec<String>,
-                  adj: &mut Vec<Vec<(usize, u8)>>,
-                  indeg: &mut Vec<usize>,
-                  outdeg: &mut Vec<usize>,
-                  next_id: &mut usize| {
-        if let Some(&id) = node_id.get(s) {
-            id
-        } else {
-            let id = *next_id;
-            *next_id += 1;
-            node_id.insert(s.to_string(), id);
-            id_node.push(s.to_string());
-            adj.push(Vec::new());
-            indeg.push(0);
-            outdeg.push(0);
-            id
+/// Largest k that fits a 2-bit packed k-mer in a `u128`.
+const MAX_K: usize = 64;
+
+fn encode(b: u8) -> Option<u8> {
+    match b {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    }
+}
+
+/// A k-mer packed two bits per base, last base in the lowest bits.
+trait Kmer: Copy + Ord + Hash + Send + Sync {
+    fn mask(len: usize) -> Self;
+    fn prepend(self, code: u8, len: usize) -> Self;
+    fn push(self, code: u8, mask: Self) -> Self;
+    fn prefix(self) -> Self;
+    fn truncate(self, mask: Self) -> Self;
+    fn base(self, i: usize) -> u8;
+}
+
+macro_rules! impl_kmer {
+    ($t:ty) => {
+        impl Kmer for $t {
+            fn mask(len: usize) -> Self {
+                if 2 * len >= <$t>::BITS as usize {
+                    <$t>::MAX
+                } else {
+                    (1 << (2 * len)) - 1
+                }
+            }
+
+            fn prepend(self, code: u8, len: usize) -> Self {
+                ((code as $t) << (2 * len)) | self
+            }
+
+            fn push(self, code: u8, mask: Self) -> Self {
+                ((self << 2) | code as $t) & mask
+            }
+
+            fn prefix(self) -> Self {
+                self >> 2
+            }
+
+            fn truncate(self, mask: Self) -> Self {
+                self & mask
+            }
+
+            fn base(self, i: usize) -> u8 {
+                ((self >> (2 * i)) & 3) as u8
+            }
         }
     };
+}
 
-    let mut seen_kmers: HashSet<String> = HashSet::new();
+impl_kmer!(u64);
+impl_kmer!(u128);
 
-    for read in reads {
-        if read.len() < k {
-            continue;
+fn unpack<K: Kmer>(kmer: K, len: usize) -> Vec<u8> {
+    (0..len)
+        .rev()
+        .map(|i| BASES[kmer.base(i) as usize])
+        .collect()
+}
+
+/// Calls `f` with every packed k-mer of `seq`, skipping windows that contain N.
+fn for_each_kmer<K: Kmer>(seq: &str, k: usize, mut f: impl FnMut(K)) {
+    let mask = K::mask(k);
+    let mut kmer = K::mask(0);
+    let mut filled = 0usize;
+    for &b in seq.as_bytes() {
+        match encode(b) {
+            Some(code) => {
+                kmer = kmer.push(code, mask);
+                filled += 1;
+                if filled >= k {
+                    f(kmer);
+                }
+            }
+            None => filled = 0,
         }
-        for i in 0..=read.len() - k {
-            let kmer = &read[i..i + k];
-            if !seen_kmers.insert(kmer.to_string()) {
-                continue;
+    }
+}
+
+/// Number of independently locked hash tables used by `count_kmers`.
+const SHARDS: usize = 64;
+/// Reads handed to one rayon task at a time.
+const CHUNK: usize = 256;
+/// K-mers buffered per shard before taking that shard's lock.
+const BATCH: usize = 1024;
+
+fn shard_of<K: Hash>(kmer: &K) -> usize {
+    let mut h = DefaultHasher::new();
+    kmer.hash(&mut h);
+    (h.finish() as usize) % SHARDS
+}
+
+/// Counts every k-mer of `seqs` on the current rayon pool. Workers buffer
+/// k-mers per shard and merge them under that shard's lock, so the result is
+/// the same whatever the number of threads.
+fn count_kmers<K: Kmer>(seqs: &[&str], k: usize) -> Vec<HashMap<K, u32>> {
+    let shards: Vec<Mutex<HashMap<K, u32>>> =
+        (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect();
+
+    let flush = |i: usize, buf: &mut Vec<K>| {
+        let mut table = shards[i].lock().unwrap();
+        for kmer in buf.drain(..) {
+            *table.entry(kmer).or_insert(0) += 1;
+        }
+    };
+
+    seqs.par_chunks(CHUNK).for_each(|chunk| {
+        let mut bufs: Vec<Vec<K>> = vec![Vec::new(); SHARDS];
+        for seq in chunk {
+            for_each_kmer(seq, k, |kmer: K| {
+                let i = shard_of(&kmer);
+                bufs[i].push(kmer);
+                if bufs[i].len() >= BATCH {
+                    flush(i, &mut bufs[i]);
+                }
+            });
+        }
+        for (i, buf) in bufs.iter_mut().enumerate() {
+            if !buf.is_empty() {
+                flush(i, buf);
             }
-            let prefix = &kmer[..k - 1];
-            let suffix = &kmer[1..];
-            let p = intern(
-                prefix,
-                &mut node_id,
-                &mut id_node,
-                &mut adj,
-                &mut indeg,
-                &mut outdeg,
-                &mut next_id,
-            );
-            let s = intern(
-                suffix,
-                &mut node_id,
-                &mut id_node,
-                &mut adj,
-                &mut indeg,
-                &mut outdeg,
-                &mut next_id,
-            );
-            let ch = kmer.as_bytes()[k - 1];
-            adj[p].push((s, ch));
-            outdeg[p] += 1;
-            indeg[s] += 1;
         }
+    });
+
+    shards
+        .into_iter()
+        .map(|m| m.into_inner().unwrap())
+        .collect()
+}
+
+/// De Bruijn graph over (k-1)-mers, stored sorted so that ids do not depend
+/// on the order in which threads saw the k-mers. Edges are implicit: bit `b`
+/// of `out[v]` means the k-mer `nodes[v] + BASES[b]` was seen.
+struct Graph<K> {
+    k: usize,
+    nodes: Vec<K>,
+    out: Vec<u8>,
+    indeg: Vec<u8>,
+}
+
+impl<K: Kmer> Graph<K> {
+    fn target(&self, v: usize, code: u8) -> usize {
+        let next = self.nodes[v].push(code, K::mask(self.k - 1));
+        self.nodes.binary_search(&next).unwrap()
+    }
+
+    fn outdeg(&self, v: usize) -> usize {
+        self.out[v].count_ones() as usize
+    }
+
+    fn edge_count(&self) -> usize {
+        (0..self.nodes.len()).map(|v| self.outdeg(v)).sum()
+    }
+
+    fn is_simple(&self, v: usize) -> bool {
+        self.indeg[v] == 1 && self.outdeg(v) == 1
     }
 
-    if next_id == 0 {
+    fn branching_nodes(&self) -> usize {
+        (0..self.nodes.len())
+            .filter(|&v| self.indeg[v] > 1 || self.outdeg(v) > 1)
+            .count()
+    }
+}
+
+fn build_graph<K: Kmer>(seqs: &[&str], k: usize) -> Graph<K> {
+    let mut kmers: Vec<K> = count_kmers::<K>(seqs, k)
+        .into_iter()
+        .flat_map(|table| table.into_keys())
+        .collect();
+    kmers.par_sort_unstable();
+
+    let node_mask = K::mask(k - 1);
+    let mut nodes: Vec<K> = kmers
+        .par_iter()
+        .flat_map_iter(|&kmer| [kmer.prefix(), kmer.truncate(node_mask)])
+        .collect();
+    nodes.par_sort_unstable();
+    nodes.dedup();
+
+    let kmer_mask = K::mask(k);
+    let has = |kmer: K| kmers.binary_search(&kmer).is_ok();
+    let out: Vec<u8> = nodes
+        .par_iter()
+        .map(|&v| {
+            (0..4u8)
+                .filter(|&b| has(v.push(b, kmer_mask)))
+                .fold(0, |m, b| m | (1 << b))
+        })
+        .collect();
+    let indeg: Vec<u8> = nodes
+        .par_iter()
+        .map(|&v| (0..4u8).filter(|&b| has(v.prepend(b, k - 1))).count() as u8)
+        .collect();
+
+    Graph {
+        k,
+        nodes,
+        out,
+        indeg,
+    }
+}
+
+fn eulerian_walk<K: Kmer>(g: &Graph<K>) -> String {
+    let n = g.nodes.len();
+    if n == 0 {
         return String::new();
     }
 
     let mut start = 0usize;
-    for v in 0..next_id {
-        if outdeg[v] == indeg[v] + 1 {
+    for v in 0..n {
+        if g.outdeg(v) == g.indeg[v] as usize + 1 {
             start = v;
             break;
         }
-        if outdeg[start] == 0 && indeg[start] == 0 && (outdeg[v] + indeg[v] > 0) {
+        if g.outdeg(start) == 0 && g.indeg[start] == 0 && (g.outdeg(v) + g.indeg[v] as usize > 0) {
             start = v;
         }
     }
 
+    let mut remaining = g.out.clone();
     let mut stack: Vec<usize> = vec![start];
     let mut edge_char_stack: Vec<u8> = Vec::new();
     let mut out_chars: Vec<u8> = Vec::new();
 
     while let Some(&v) = stack.last() {
-        if let Some((next, ch)) = adj[v].pop() {
-            stack.push(next);
-            edge_char_stack.push(ch);
+        if remaining[v] != 0 {
+            let code = 7 - remaining[v].leading_zeros() as u8;
+            remaining[v] &= !(1 << code);
+            stack.push(g.target(v, code));
+            edge_char_stack.push(BASES[code as usize]);
         } else {
             stack.pop();
             if let Some(ch) = edge_char_stack.pop() {
@@ -219,21 +432,187 @@ fn assemble_debruijn(reads: &[String], k: usize) -> String {
         return String::new();
     }
 
-    let mut seq = id_node[start].clone().into_bytes();
+    let mut seq = unpack(g.nodes[start], g.k - 1);
     seq.extend(out_chars);
     String::from_utf8(seq).unwrap()
 }
 
-fn choose_k(reads: &[String]) -> usize {
+fn assemble_debruijn(reads: &[String], k: usize) -> String {
+    assert!(k >= 2, "k trebuie să fie ≥ 2");
+    assert!(k <= MAX_K, "k trebuie să fie ≤ {}", MAX_K);
+    let min_read = reads.iter().map(|r| r.len()).min().expect("fără reads");
+    assert!(k <= min_read, "k trebuie ≤ lungimea minimă a read-ului");
+
+    let seqs: Vec<&str> = reads.iter().map(|r| r.as_str()).collect();
+    if k <= 32 {
+        eulerian_walk(&build_graph::<u64>(&seqs, k))
+    } else {
+        eulerian_walk(&build_graph::<u128>(&seqs, k))
+    }
+}
+
+/// Maximal non-branching paths of the graph; isolated cycles are emitted once.
+fn unitigs<K: Kmer>(g: &Graph<K>) -> Vec<String> {
+    let mut used = vec![0u8; g.nodes.len()];
+    let mut contigs = Vec::new();
+
+    let mut walk = |v: usize, code: u8, used: &mut Vec<u8>| {
+        let mut seq = unpack(g.nodes[v], g.k - 1);
+        used[v] |= 1 << code;
+        seq.push(BASES[code as usize]);
+        let mut w = g.target(v, code);
+        while g.is_simple(w) && used[w] == 0 {
+            let code = g.out[w].trailing_zeros() as u8;
+            used[w] |= 1 << code;
+            seq.push(BASES[code as usize]);
+            w = g.target(w, code);
+        }
+        contigs.push(String::from_utf8(seq).unwrap());
+    };
+
+    for v in 0..g.nodes.len() {
+        if g.is_simple(v) {
+            continue;
+        }
+        for code in 0..4u8 {
+            if g.out[v] & (1 << code) != 0 {
+                walk(v, code, &mut used);
+            }
+        }
+    }
+    for v in 0..g.nodes.len() {
+        if g.out[v] & !used[v] != 0 {
+            walk(v, g.out[v].trailing_zeros() as u8, &mut used);
+        }
+    }
+    contigs
+}
+
+fn n50(contigs: &[String]) -> usize {
+    let mut lens: Vec<usize> = contigs.iter().map(|c| c.len()).collect();
+    lens.sort_unstable_by(|a, b| b.cmp(a));
+    let half = lens.iter().sum::<usize>().div_ceil(2);
+    let mut acc = 0usize;
+    for len in lens {
+        acc += len;
+        if acc >= half {
+            return len;
+        }
+    }
+    0
+}
+
+struct KRound {
+    k: usize,
+    nodes: usize,
+    edges: usize,
+    branching: usize,
+    contigs: Vec<String>,
+    reconstruction: String,
+    peak_kb: Option<u64>,
+}
+
+impl KRound {
+    fn longest(&self) -> usize {
+        self.contigs.iter().map(|c| c.len()).max().unwrap_or(0)
+    }
+
+    fn total_len(&self) -> usize {
+        self.contigs.iter().map(|c| c.len()).sum()
+    }
+}
+
+fn assemble_round<K: Kmer>(seqs: &[&str], k: usize) -> KRound {
+    let g = build_graph::<K>(seqs, k);
+    let contigs = unitigs(&g);
+    let reconstruction = eulerian_walk(&g);
+    KRound {
+        k,
+        nodes: g.nodes.len(),
+        edges: g.edge_count(),
+        branching: g.branching_nodes(),
+        contigs,
+        reconstruction,
+        peak_kb: peak_memory_kb(),
+    }
+}
+
+/// Assembles at each k in turn, feeding the contigs of the previous round
+/// back in as pseudo-reads so that longer k-mers can bridge read gaps.
+fn assemble_multi_k(reads: &[String], ks: &[usize]) -> Vec<KRound> {
+    let mut rounds: Vec<KRound> = Vec::with_capacity(ks.len());
+    for &k in ks {
+        assert!(
+            (2..=MAX_K).contains(&k),
+            "k trebuie să fie între 2 și {}",
+            MAX_K
+        );
+        let prev: &[String] = rounds.last().map_or(&[], |r| r.contigs.as_slice());
+        let seqs: Vec<&str> = reads
+            .iter()
+            .chain(prev.iter().filter(|c| c.len() >= k))
+            .map(|s| s.as_str())
+            .collect();
+        let round = if k <= 32 {
+            assemble_round::<u64>(&seqs, k)
+        } else {
+            assemble_round::<u128>(&seqs, k)
+        };
+        rounds.push(round);
+    }
+    rounds
+}
+
+/// Rejects a k outside `2..=max`, `max` being at most [`MAX_K`] as k-mers
+/// are packed into 128 bits.
+fn check_k(k: usize, max: usize) -> Result<(), Box<dyn Error>> {
+    if (2..=max.min(MAX_K)).contains(&k) {
+        Ok(())
+    } else {
+        Err(format!("k trebuie să fie între 2 și {}, nu {}", max.min(MAX_K), k).into())
+    }
+}
+
+fn default_ks(reads: &[String]) -> Vec<usize> {
     let min_read = reads.iter().map(|r| r.len()).min().unwrap_or(100);
-    let mut k = 51usize;
-    if k >= min_read {
-        k = std::cmp::max(21, min_read.saturating_sub(1));
+    let max_k = min_read.saturating_sub(1).clamp(21, MAX_K);
+    (21..=max_k).step_by(10).collect()
+}
+
+/// Peak resident set size of this process, from `/proc/self/status` (Linux only).
+fn peak_memory_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find_map(|l| l.strip_prefix("VmHWM:"))?;
+    line.trim().trim_end_matches("kB").trim().parse().ok()
+}
+
+fn format_memory(kb: Option<u64>) -> String {
+    match kb {
+        Some(kb) => format!("{:.1} MB", kb as f64 / 1024.0),
+        None => "n/a".to_string(),
     }
-    if k % 2 == 0 {
-        k -= 1;
+}
+
+fn print_rounds(rounds: &[KRound]) {
+    println!(
+        "{:>5} {:>8} {:>8} {:>10} {:>8} {:>10} {:>8} {:>8} {:>10}",
+        "k", "nodes", "edges", "branching", "contigs", "total", "longest", "N50", "peak mem"
+    );
+    for r in rounds {
+        println!(
+            "{:>5} {:>8} {:>8} {:>10} {:>8} {:>10} {:>8} {:>8} {:>10}",
+            r.k,
+            r.nodes,
+            r.edges,
+            r.branching,
+            r.contigs.len(),
+            r.total_len(),
+            r.longest(),
+            n50(&r.contigs),
+            format_memory(r.peak_kb)
+        );
     }
-    k
+    println!();
 }
 
 fn revcomp(s: &str) -> String {
@@ -251,35 +630,91 @@ fn revcomp(s: &str) -> String {
     out
 }
 
-fn kmers(s: &str, k: usize) -> HashSet<String> {
+fn kmers<K: Kmer>(s: &str, k: usize) -> HashSet<K> {
     let mut set = HashSet::new();
-    if s.len() >= k {
-        for i in 0..=s.len() - k {
-            set.insert(s[i..i + k].to_string());
-        }
-    }
+    for_each_kmer(s, k, |kmer| {
+        set.insert(kmer);
+    });
     set
 }
 
-fn fraction_kmers_in(a: &str, b: &str, k: usize) -> f64 {
-    if a.len() < k {
-        return 0.0;
-    }
-    let aa = kmers(a, k);
-    let bb = kmers(b, k);
+fn fraction_in<K: Kmer>(a: &str, b: &str, k: usize) -> f64 {
+    let aa: HashSet<K> = kmers(a, k);
     if aa.is_empty() {
         return 0.0;
     }
+    let bb: HashSet<K> = kmers(b, k);
     let hit = aa.iter().filter(|x| bb.contains(*x)).count();
     hit as f64 / aa.len() as f64
 }
 
+fn fraction_kmers_in(a: &str, b: &str, k: usize) -> f64 {
+    if a.len() < k {
+        return 0.0;
+    }
+    if k <= 32 {
+        fraction_in::<u64>(a, b, k)
+    } else {
+        fraction_in::<u128>(a, b, k)
+    }
+}
+
+/// Times graph construction at 1, 2, 4 and 8 threads and checks that every
+/// run produced the same graph.
+fn bench_threads(reads: &[String], k: usize) -> Result<(), Box<dyn Error>> {
+    let seqs: Vec<&str> = reads.iter().map(|r| r.as_str()).collect();
+    let mut baseline: Option<(Vec<u128>, Vec<u8>, Vec<u8>)> = None;
+    let mut base_ms = 0.0;
+
+    println!("Benchmark: k-mer counting + graph construction, k = {}", k);
+    println!(
+        "{:>8} {:>12} {:>8} {:>10}",
+        "threads", "wall (ms)", "speedup", "nodes"
+    );
+    for threads in [1usize, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        let t0 = Instant::now();
+        let g = pool.install(|| build_graph::<u128>(&seqs, k));
+        let ms = t0.elapsed().as_secs_f64() * 1000.0;
+
+        let result = (g.nodes, g.out, g.indeg);
+        match &baseline {
+            None => {
+                base_ms = ms;
+                baseline = Some(result);
+            }
+            Some(b) if *b != result => {
+                return Err(format!("graph built with {} threads differs", threads).into());
+            }
+            Some(_) => {}
+        }
+        println!(
+            "{:>8} {:>12.1} {:>7.2}x {:>10}",
+            threads,
+            ms,
+            base_ms / ms,
+            baseline.as_ref().map_or(0, |b| b.0.len())
+        );
+    }
+    println!();
+    Ok(())
+}
+
+/// Sequences longer than this are summarised instead of printed in full.
+const PRINT_LIMIT: usize = 10_000;
+
 fn print_wrapped(title: &str, seq: &str, width: usize) {
     println!("{}", title);
     if seq.is_empty() {
         println!("[gol]\n");
         return;
     }
+    if seq.len() > PRINT_LIMIT {
+        println!("[{} bases, not printed]\n", seq.len());
+        return;
+    }
     for chunk in seq.as_bytes().chunks(width) {
         println!("{}", std::str::from_utf8(chunk).unwrap());
     }
@@ -297,13 +732,20 @@ fn main() -> Result<(), Box<dyn Error>> {
             s
         }
         None => {
-            let s: u64 = rand::thread_rng().random();
+            let s: u64 = rand::rng().random();
             println!("chosen seed: {}", s);
             s
         }
     };
     let mut rng_for_choose = StdRng::seed_from_u64(seed);
 
+    if let Some(t) = arg_threads() {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(t)
+            .build_global()?;
+    }
+    println!("Threads: {}", rayon::current_num_threads());
+
     let records = read_fasta_records(&path)?;
     println!("FASTAs found: {}", records.len());
 
@@ -324,7 +766,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("Read {} bases from {}", original.len(), path);
 
     let seed_reads: u64 = rng_for_choose.random();
-    let reads = sample_reads(original.as_str(), 2000, 100, 150, seed_reads);
+    let n_reads = match arg_coverage() {
+        Some(c) => ((c * original.len() as f64) / 125.0).ceil().max(1.0) as usize,
+        None => 2000,
+    };
+    let reads = sample_reads(original.as_str(), n_reads, 100, 150, seed_reads);
     let avg_len: f64 = reads.iter().map(|r| r.len()).sum::<usize>() as f64 / reads.len() as f64;
     let cov = (reads.len() as f64) * avg_len / (original.len().max(1) as f64);
     println!(
@@ -334,10 +780,42 @@ fn main() -> Result<(), Box<dyn Error>> {
         cov
     );
 
-    let k = arg_k().unwrap_or_else(|| choose_k(&reads));
-    let assembled = assemble_debruijn(&reads, k);
+    // Every k-mer must fit in the shortest read.
+    let min_read = reads.iter().map(|r| r.len()).min().unwrap_or(0);
+    if arg_flag("--bench") {
+        let k = arg_k().unwrap_or(31);
+        check_k(k, min_read)?;
+        return bench_threads(&reads, k);
+    }
+
+    let (k, assembled) = match arg_k() {
+        Some(k) => {
+            check_k(k, min_read)?;
+            (k, assemble_debruijn(&reads, k))
+        }
+        None => {
+            let ks = arg_ks().unwrap_or_else(|| default_ks(&reads));
+            for &k in &ks {
+                check_k(k, MAX_K)?;
+            }
+            println!("Multi-k assembly over k = {:?}", ks);
+            let rounds = assemble_multi_k(&reads, &ks);
+            print_rounds(&rounds);
+            let best = rounds
+                .into_iter()
+                .max_by(|a, b| {
+                    n50(&a.contigs)
+                        .cmp(&n50(&b.contigs))
+                        .then(b.contigs.len().cmp(&a.contigs.len()))
+                        .then(a.k.cmp(&b.k))
+                })
+                .expect("fără valori k");
+            (best.k, best.reconstruction)
+        }
+    };
 
     println!("k used: {}", k);
+    println!("Peak memory: {}", format_memory(peak_memory_kb()));
     println!(
         "Original length: {} | Assembled length: {}",
         original.len(),
@@ -367,7 +845,6 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     let assembled_rc = revcomp(assembled.as_str());
-    let original_rc = revcomp(original.as_str());
 
     let frac_fwd = fraction_kmers_in(assembled.as_str(), original.as_str(), k);
     let frac_rc = fraction_kmers_in(assembled_rc.as_str(), original.as_str(), k);
@@ -375,8 +852,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     println!(
         "With k = {}, fraction of k-mers from the assembled contig found in the original (or its reverse complement) = {:.2}%.",
-        100.0 * frac,
-        k
+        k,
+        100.0 * frac
     );
 
     Ok(())
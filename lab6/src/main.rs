@@ -1,5 +1,8 @@
+use plotters::prelude::*;
 use rand::{Rng, SeedableRng, rngs::StdRng};
-use std::cmp::{max, min};
+use std::cmp::min;
+use std::env;
+use std::error::Error;
 use std::fs;
 use std::path::Path;
 
@@ -10,6 +13,170 @@ struct Fragment {
     seq: String,
 }
 
+/// One band on the gel: fragment size and the amount of DNA in it.
+#[derive(Debug, Clone, Copy)]
+struct Band {
+    bp: usize,
+    ng: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Lane {
+    name: String,
+    bands: Vec<Band>,
+}
+
+/// Run conditions. Migration follows the Ferguson relation
+/// `mu = mu0 * exp(-Kr * C)`, with the retardation coefficient `Kr`
+/// growing with the square root of fragment length, so positions depend on
+/// the gel and the run rather than on the fragments loaded.
+#[derive(Debug, Clone, Copy)]
+struct GelSettings {
+    agarose_pct: f64,
+    volts_per_cm: f64,
+    minutes: f64,
+    length_cm: f64,
+}
+
+/// Free mobility, in cm per (V/cm) per minute.
+const MU0: f64 = 0.039;
+/// Retardation per % agarose for a 1 kb fragment.
+const KR_1KB: f64 = 0.73;
+/// DNA loaded in each sample lane, in ng.
+const SAMPLE_NG: f64 = 200.0;
+
+impl GelSettings {
+    fn migration_cm(&self, bp: usize) -> f64 {
+        let kr = KR_1KB * (bp.max(1) as f64 / 1000.0).sqrt();
+        let mu = MU0 * (-kr * self.agarose_pct).exp();
+        mu * self.volts_per_cm * self.minutes
+    }
+
+    fn on_gel(&self, bp: usize) -> bool {
+        self.migration_cm(bp) <= self.length_cm
+    }
+}
+
+impl Default for GelSettings {
+    fn default() -> Self {
+        GelSettings {
+            agarose_pct: 1.0,
+            volts_per_cm: 5.0,
+            minutes: 45.0,
+            length_cm: 10.0,
+        }
+    }
+}
+
+/// Standard ladders as (bp, ng) for a 0.5 µg load.
+fn ladder(name: &str) -> Option<Lane> {
+    let (label, bands): (&str, &[(usize, f64)]) = match name.to_ascii_lowercase().as_str() {
+        "1kb" => (
+            "1 kb",
+            &[
+                (10000, 42.0),
+                (8000, 42.0),
+                (6000, 50.0),
+                (5000, 42.0),
+                (4000, 33.0),
+                (3000, 125.0),
+                (2000, 48.0),
+                (1500, 36.0),
+                (1000, 42.0),
+                (500, 42.0),
+            ],
+        ),
+        "100bp" => (
+            "100 bp",
+            &[
+                (1517, 45.0),
+                (1200, 35.0),
+                (1000, 95.0),
+                (900, 27.0),
+                (800, 24.0),
+                (700, 21.0),
+                (600, 18.0),
+                (500, 97.0),
+                (400, 38.0),
+                (300, 29.0),
+                (200, 25.0),
+                (100, 48.0),
+            ],
+        ),
+        "lambda" | "hindiii" | "lambda-hindiii" => (
+            "λ-HindIII",
+            &[
+                (23130, 238.0),
+                (9416, 97.0),
+                (6557, 68.0),
+                (4361, 45.0),
+                (2322, 24.0),
+                (2027, 21.0),
+                (564, 6.0),
+                (125, 1.0),
+            ],
+        ),
+        _ => return None,
+    };
+    Some(Lane {
+        name: label.to_string(),
+        bands: bands.iter().map(|&(bp, ng)| Band { bp, ng }).collect(),
+    })
+}
+
+/// Equimolar fragments: each band's mass is proportional to its length.
+fn lane_from_sizes(name: &str, sizes: &[usize]) -> Lane {
+    let total: usize = sizes.iter().sum();
+    let bands = sizes
+        .iter()
+        .map(|&bp| Band {
+            bp,
+            ng: SAMPLE_NG * bp as f64 / total.max(1) as f64,
+        })
+        .collect();
+    Lane {
+        name: name.to_string(),
+        bands,
+    }
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let mut it = env::args().skip(1);
+    let eq = format!("{}=", name);
+    while let Some(tok) = it.next() {
+        if tok == name {
+            return it.next();
+        } else if let Some(v) = tok.strip_prefix(&eq) {
+            return Some(v.to_string());
+        }
+    }
+    None
+}
+
+fn arg_f64(name: &str) -> Option<f64> {
+    arg_value(name).and_then(|v| v.parse().ok())
+}
+
+fn arg_usize(name: &str) -> Option<usize> {
+    arg_value(name).and_then(|v| v.parse().ok())
+}
+
+/// Arguments that are neither flags nor flag values.
+fn positional_args() -> Vec<String> {
+    let mut out = Vec::new();
+    let mut it = env::args().skip(1);
+    while let Some(tok) = it.next() {
+        if tok.starts_with("--") {
+            if !tok.contains('=') {
+                it.next();
+            }
+        } else {
+            out.push(tok);
+        }
+    }
+    out
+}
+
 fn read_fasta_first_record<P: AsRef<Path>>(path: P) -> Result<(String, String), String> {
     let content = fs::read_to_string(&path).map_err(|e| format!("cant read file: {}", e))?;
     let mut header = String::new();
@@ -20,9 +187,9 @@ fn read_fasta_first_record<P: AsRef<Path>>(path: P) -> Result<(String, String),
         if line.is_empty() {
             continue;
         }
-        if line.starts_with('>') {
+        if let Some(h) = line.strip_prefix('>') {
             if seq.is_empty() {
-                header = line[1..].to_string();
+                header = h.to_string();
             } else {
                 break;
             }
@@ -39,49 +206,321 @@ fn read_fasta_first_record<P: AsRef<Path>>(path: P) -> Result<(String, String),
     Ok((header, seq))
 }
 
-fn len_to_position(len: usize, min_bp: usize, max_bp: usize, gel_height: usize) -> usize {
-    let eps = 1e-9_f64;
-    let lmin = (min_bp as f64).log10();
-    let lmax = (max_bp as f64).log10();
-    let lcur = (len as f64).log10();
-    let norm = (lmax - lcur) / ((lmax - lmin) + eps);
-    let top_margin = 2usize;
-    let bottom_margin = 1usize;
-    let usable = gel_height.saturating_sub(top_margin + bottom_margin);
-    top_margin + (norm * usable as f64).round() as usize
+fn random_fragments(genome: &str, rng: &mut StdRng) -> Vec<Fragment> {
+    let mut fragments = Vec::new();
+    for _ in 0..10 {
+        let max_len_here = genome.len().clamp(100, 3000);
+        let len = rng.random_range(100..=max_len_here);
+        let start_max = genome.len().saturating_sub(len);
+        let start = if start_max == 0 {
+            0
+        } else {
+            rng.random_range(0..=start_max)
+        };
+        let seq = genome[start..min(start + len, genome.len())].to_string();
+        fragments.push(Fragment { start, len, seq });
+    }
+    fragments
+}
+
+#[derive(Debug, Clone)]
+struct PrimerPair {
+    name: String,
+    fwd: String,
+    rev: String,
 }
 
-fn draw_gel(fragments: &[Fragment]) -> String {
+#[derive(Debug, Clone, Copy)]
+struct PcrSettings {
+    max_mismatches: usize,
+    clamp: usize,
+    max_product: usize,
+}
+
+/// A primer annealed to the genome. `pos` is the 0-based start of the
+/// binding window on the top strand; `plus` primers extend rightwards.
+#[derive(Debug, Clone, Copy)]
+struct Site {
+    primer: usize,
+    pos: usize,
+    plus: bool,
+    mismatches: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Amplicon {
+    left: usize,
+    right: usize,
+    start: usize,
+    end: usize,
+    mismatches: (usize, usize),
+    seq: String,
+}
+
+fn revcomp(s: &str) -> String {
+    s.chars()
+        .rev()
+        .map(|c| match c {
+            'A' => 'T',
+            'T' => 'A',
+            'C' => 'G',
+            'G' => 'C',
+            'R' => 'Y',
+            'Y' => 'R',
+            'K' => 'M',
+            'M' => 'K',
+            'B' => 'V',
+            'V' => 'B',
+            'D' => 'H',
+            'H' => 'D',
+            other => other,
+        })
+        .collect()
+}
+
+/// IUPAC primer base against a genome base; N in the genome never matches.
+fn base_matches(primer: u8, genome: u8) -> bool {
+    let allowed: &[u8] = match primer {
+        b'A' | b'C' | b'G' | b'T' => return primer == genome,
+        b'R' => b"AG",
+        b'Y' => b"CT",
+        b'S' => b"CG",
+        b'W' => b"AT",
+        b'K' => b"GT",
+        b'M' => b"AC",
+        b'B' => b"CGT",
+        b'D' => b"AGT",
+        b'H' => b"ACT",
+        b'V' => b"ACG",
+        b'N' => b"ACGT",
+        _ => return false,
+    };
+    allowed.contains(&genome)
+}
+
+/// Mismatches between `primer` and `window`, or `None` if there are more
+/// than `max` or any of them falls in the last `clamp` bases at the 3' end.
+fn primer_mismatches(primer: &[u8], window: &[u8], clamp: usize, max: usize) -> Option<usize> {
+    let three_prime = primer.len().saturating_sub(clamp);
+    let mut mm = 0;
+    for (i, (&p, &g)) in primer.iter().zip(window).enumerate() {
+        if !base_matches(p, g) {
+            if i >= three_prime {
+                return None;
+            }
+            mm += 1;
+            if mm > max {
+                return None;
+            }
+        }
+    }
+    Some(mm)
+}
+
+/// Binding sites of `primer` on both strands. `rc` is the reverse complement
+/// of `genome`, so a match at `p` in `rc` anneals at `n - p - len` on top.
+fn find_sites(
+    primer_idx: usize,
+    primer: &str,
+    genome: &str,
+    rc: &str,
+    settings: &PcrSettings,
+) -> Vec<Site> {
+    let p = primer.as_bytes();
+    let n = genome.len();
+    let mut sites = Vec::new();
+    if p.is_empty() || n < p.len() {
+        return sites;
+    }
+    for (strand, plus) in [(genome.as_bytes(), true), (rc.as_bytes(), false)] {
+        for (i, window) in strand.windows(p.len()).enumerate() {
+            if let Some(mm) = primer_mismatches(p, window, settings.clamp, settings.max_mismatches)
+            {
+                let pos = if plus { i } else { n - i - p.len() };
+                sites.push(Site {
+                    primer: primer_idx,
+                    pos,
+                    plus,
+                    mismatches: mm,
+                });
+            }
+        }
+    }
+    sites
+}
+
+/// Every plus-strand site followed by a minus-strand site within
+/// `max_product` gives a product, whichever primer of the pair binds where.
+fn run_pcr(pair: &PrimerPair, genome: &str, settings: &PcrSettings) -> Vec<Amplicon> {
+    let primers = [pair.fwd.as_str(), pair.rev.as_str()];
+    let rc = revcomp(genome);
+    let mut sites: Vec<Site> = Vec::new();
+    for (i, primer) in primers.iter().enumerate() {
+        sites.extend(find_sites(i, primer, genome, &rc, settings));
+    }
+
+    let mut products = Vec::new();
+    for l in sites.iter().filter(|s| s.plus) {
+        for r in sites.iter().filter(|s| !s.plus) {
+            let (lp, rp) = (primers[l.primer], primers[r.primer]);
+            let end = r.pos + rp.len();
+            if r.pos < l.pos || end < l.pos + lp.len() || end - l.pos > settings.max_product {
+                continue;
+            }
+            // The template with the primers in place of their sites; where
+            // the sites overlap, the reverse primer is written last.
+            let mut product = genome.as_bytes()[l.pos..end].to_vec();
+            let len = product.len();
+            product[..lp.len()].copy_from_slice(lp.as_bytes());
+            product[len - rp.len()..].copy_from_slice(revcomp(rp).as_bytes());
+            let seq = String::from_utf8_lossy(&product).into_owned();
+            products.push(Amplicon {
+                left: l.primer,
+                right: r.primer,
+                start: l.pos,
+                end,
+                mismatches: (l.mismatches, r.mismatches),
+                seq,
+            });
+        }
+    }
+    products.sort_by_key(|a| (a.start, a.end));
+    products
+}
+
+/// Primer pairs from a TSV of `name<TAB>forward<TAB>reverse`; `#` lines and
+/// a header row are skipped. Any other row whose primers are not IUPAC
+/// sequences is an error.
+fn read_primer_pairs(path: &str) -> Result<Vec<PrimerPair>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let mut pairs = Vec::new();
+    let mut first = true;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cols: Vec<&str> = line.split('\t').map(|c| c.trim()).collect();
+        if cols.len() < 3 {
+            return Err(format!("expected name, forward, reverse in '{}'", line).into());
+        }
+        let (fwd, rev) = (cols[1].to_ascii_uppercase(), cols[2].to_ascii_uppercase());
+        let is_primer = |s: &str| s.bytes().all(|b| b"ACGTRYSWKMBDHVN".contains(&b));
+        let header = std::mem::replace(&mut first, false);
+        if !is_primer(&fwd) || !is_primer(&rev) {
+            if header {
+                continue;
+            }
+            return Err(format!("primers must be IUPAC sequences in '{}'", line).into());
+        }
+        pairs.push(PrimerPair {
+            name: cols[0].to_string(),
+            fwd,
+            rev,
+        });
+    }
+    Ok(pairs)
+}
+
+fn primer_pairs() -> Result<Vec<PrimerPair>, Box<dyn Error>> {
+    if let Some(path) = arg_value("--primers") {
+        return read_primer_pairs(&path);
+    }
+    match (arg_value("--fwd"), arg_value("--rev")) {
+        (Some(f), Some(r)) => Ok(vec![PrimerPair {
+            name: "pair".to_string(),
+            fwd: f.to_ascii_uppercase(),
+            rev: r.to_ascii_uppercase(),
+        }]),
+        (None, None) => Ok(Vec::new()),
+        _ => Err("--fwd and --rev must be given together".into()),
+    }
+}
+
+fn print_wrapped(seq: &str, width: usize) {
+    for chunk in seq.as_bytes().chunks(width) {
+        println!("{}", std::str::from_utf8(chunk).unwrap());
+    }
+}
+
+const LANE_WIDTH: usize = 12;
+const INTENSITY: [char; 5] = ['.', ':', '-', '=', '#'];
+
+/// Bands of equal size within a lane run together, so their masses add up.
+fn merged_bands(lane: &Lane) -> Vec<Band> {
+    let mut bands = lane.bands.clone();
+    bands.sort_by_key(|b| std::cmp::Reverse(b.bp));
+    let mut out: Vec<Band> = Vec::new();
+    for b in bands {
+        match out.last_mut() {
+            Some(last) if last.bp == b.bp => last.ng += b.ng,
+            _ => out.push(b),
+        }
+    }
+    out
+}
+
+fn max_band_ng(lanes: &[Lane]) -> f64 {
+    lanes
+        .iter()
+        .flat_map(merged_bands)
+        .map(|b| b.ng)
+        .fold(0.0, f64::max)
+}
+
+fn draw_gel(lanes: &[Lane], gel: &GelSettings) -> String {
     let gel_height = 34usize;
-    let gel_width = 30usize;
+    let gel_width = lanes.len() * LANE_WIDTH + 2;
+    let top_margin = 2usize;
+    let usable = gel_height - top_margin - 1;
     let mut canvas = vec![vec![' '; gel_width]; gel_height];
 
-    for y in 0..gel_height {
-        canvas[y][0] = '|';
-        canvas[y][gel_width - 1] = '|';
+    for row in canvas.iter_mut() {
+        row[0] = '|';
+        row[gel_width - 1] = '|';
     }
-    for x in 0..gel_width {
-        canvas[0][x] = if x == 0 || x == gel_width - 1 {
+    for (x, cell) in canvas[0].iter_mut().enumerate() {
+        *cell = if x == 0 || x == gel_width - 1 {
             '+'
         } else {
             '-'
         };
     }
+    for (i, _) in lanes.iter().enumerate() {
+        let x0 = 1 + i * LANE_WIDTH;
+        for cell in &mut canvas[1][x0 + 3..x0 + LANE_WIDTH - 3] {
+            *cell = '_';
+        }
+    }
 
-    let min_bp = fragments.iter().map(|f| f.len).min().unwrap_or(100);
-    let max_bp = fragments.iter().map(|f| f.len).max().unwrap_or(3000);
-
-    for frag in fragments {
-        let y = min(
-            gel_height - 1,
-            len_to_position(frag.len, min_bp, max_bp, gel_height),
-        );
-        for x in 2..gel_width - 2 {
-            canvas[y][x] = '=';
+    let max_ng = max_band_ng(lanes);
+    for (i, lane) in lanes.iter().enumerate() {
+        let x0 = 1 + i * LANE_WIDTH;
+        for band in merged_bands(lane) {
+            if !gel.on_gel(band.bp) {
+                continue;
+            }
+            let d = gel.migration_cm(band.bp) / gel.length_cm;
+            let y = top_margin + (d * usable as f64).round() as usize;
+            let level = ((band.ng / max_ng).sqrt() * (INTENSITY.len() - 1) as f64).round();
+            let ch = INTENSITY[level as usize];
+            let rank = |c: char| INTENSITY.iter().position(|&i| i == c);
+            let row = &mut canvas[min(y, gel_height - 1)];
+            for cell in &mut row[x0 + 2..x0 + LANE_WIDTH - 2] {
+                if rank(*cell) < rank(ch) {
+                    *cell = ch;
+                }
+            }
         }
     }
 
     let mut out = String::new();
+    out.push(' ');
+    for lane in lanes {
+        let name: String = lane.name.chars().take(LANE_WIDTH - 1).collect();
+        out.push_str(&format!("{:^width$}", name, width = LANE_WIDTH));
+    }
+    out.push('\n');
     for row in canvas {
         for ch in row {
             out.push(ch);
@@ -91,48 +530,238 @@ fn draw_gel(fragments: &[Fragment]) -> String {
     out
 }
 
-fn main() {
-    let mut rng = StdRng::seed_from_u64(42);
+/// Renders the gel to PNG or SVG, chosen by the file extension.
+fn render_gel(lanes: &[Lane], gel: &GelSettings, path: &str) -> Result<(), Box<dyn Error>> {
+    let lane_px = 90u32;
+    let w = 80 + lanes.len() as u32 * lane_px + 20;
+    let h = 560u32;
+    if path.to_ascii_lowercase().ends_with(".svg") {
+        let root = SVGBackend::new(path, (w, h)).into_drawing_area();
+        paint_gel(&root, lanes, gel, lane_px)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(path, (w, h)).into_drawing_area();
+        paint_gel(&root, lanes, gel, lane_px)?;
+        root.present()?;
+    }
+    Ok(())
+}
 
-    let path = std::env::args().nth(1).expect("lab6 <path_to_fasta>");
+fn paint_gel<DB: DrawingBackend>(
+    root: &DrawingArea<DB, plotters::coord::Shift>,
+    lanes: &[Lane],
+    gel: &GelSettings,
+    lane_px: u32,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let (w, h) = root.dim_in_pixel();
+    let (left, top, bottom) = (80i32, 60i32, 20i32);
+    let usable = (h as i32 - top - bottom) as f64;
+    let label_font = ("sans-serif", 14).into_font().color(&WHITE);
+    let size_font = ("sans-serif", 11)
+        .into_font()
+        .color(&RGBColor(200, 200, 200));
 
-    let (header, genome) = read_fasta_first_record(path).expect("error reading FASTA");
+    root.fill(&RGBColor(15, 15, 25))?;
+    root.draw(&Rectangle::new(
+        [
+            (left - 10, top - 15),
+            (w as i32 - 10, h as i32 - bottom + 5),
+        ],
+        RGBColor(35, 35, 50).filled(),
+    ))?;
+    root.draw(&Text::new(
+        format!(
+            "{:.1}% agarose, {:.0} V/cm, {:.0} min",
+            gel.agarose_pct, gel.volts_per_cm, gel.minutes
+        ),
+        (left, 5),
+        label_font.clone(),
+    ))?;
 
-    let mut fragments = Vec::new();
-    for _ in 0..10 {
-        let max_len_here = max(100, min(3000, genome.len()));
-        let len = rng.gen_range(100..=max_len_here);
-        let start_max = genome.len().saturating_sub(len);
-        let start = if start_max == 0 {
-            0
-        } else {
-            rng.gen_range(0..=start_max)
-        };
-        let seq = genome[start..start + len].to_string();
-        fragments.push(Fragment { start, len, seq });
+    let max_ng = max_band_ng(lanes);
+    for (i, lane) in lanes.iter().enumerate() {
+        let x0 = left + i as i32 * lane_px as i32;
+        let x1 = x0 + lane_px as i32 - 20;
+        root.draw(&Text::new(
+            lane.name.clone(),
+            (x0, top - 40),
+            label_font.clone(),
+        ))?;
+        root.draw(&Rectangle::new(
+            [(x0, top - 12), (x1, top - 6)],
+            RGBColor(90, 90, 100).filled(),
+        ))?;
+        for band in merged_bands(lane) {
+            if !gel.on_gel(band.bp) {
+                continue;
+            }
+            let y = top + (gel.migration_cm(band.bp) / gel.length_cm * usable).round() as i32;
+            let level = (band.ng / max_ng).sqrt();
+            let shade = (60.0 + 195.0 * level) as u8;
+            root.draw(&Rectangle::new(
+                [(x0, y - 2), (x1, y + 2)],
+                RGBColor(shade, shade, (shade as f64 * 0.85) as u8).filled(),
+            ))?;
+            if i == 0 {
+                root.draw(&Text::new(
+                    format!("{}", band.bp),
+                    (8, y - 6),
+                    size_font.clone(),
+                ))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let paths = positional_args();
+    if paths.is_empty() {
+        return Err("lab6 <path_to_fasta>... [--fwd SEQ --rev SEQ | --primers pairs.tsv] [--mismatches 2] [--clamp 3] [--max-product 5000] [--agarose 1.0] [--volts 5] [--minutes 45] [--ladder 1kb,100bp,lambda] [--png gel.png] [--svg gel.svg]".into());
     }
 
-    println!("Header FASTA: {}", header);
-    println!("FASTA length: {} nt", genome.len());
-    println!("Extracted fragments: {}", fragments.len());
-    println!();
+    let defaults = GelSettings::default();
+    let gel = GelSettings {
+        agarose_pct: arg_f64("--agarose").unwrap_or(defaults.agarose_pct),
+        volts_per_cm: arg_f64("--volts").unwrap_or(defaults.volts_per_cm),
+        minutes: arg_f64("--minutes").unwrap_or(defaults.minutes),
+        length_cm: arg_f64("--length").unwrap_or(defaults.length_cm),
+    };
 
-    println!("{:<6} {:<8} {:<8}", "Idx", "Start", "Length(bp)");
-    for (i, f) in fragments.iter().enumerate() {
-        println!("{:<6} {:<8} {:<8}", i, f.start, f.len);
+    let mut lanes: Vec<Lane> = Vec::new();
+    for name in arg_value("--ladder")
+        .unwrap_or_else(|| "1kb".to_string())
+        .split(',')
+    {
+        match ladder(name.trim()) {
+            Some(l) => lanes.push(l),
+            None => eprintln!("Unknown ladder '{}' (use 1kb, 100bp or lambda)", name),
+        }
     }
 
-    println!("\nGel electrophoresis (ASCII):\n");
-    let gel = draw_gel(&fragments);
-    println!("{}", gel);
+    let pairs = primer_pairs()?;
+    let pcr = PcrSettings {
+        max_mismatches: arg_usize("--mismatches").unwrap_or(2),
+        clamp: arg_usize("--clamp").unwrap_or(3),
+        max_product: arg_usize("--max-product").unwrap_or(5000),
+    };
+
+    for path in &paths {
+        let (header, genome) = read_fasta_first_record(path)?;
+        let stem = Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| format!("lane {}", lanes.len() + 1));
 
-    for (i, f) in fragments.iter().take(10).enumerate() {
-        let preview: String = f.seq.chars().take(40).collect();
+        println!("Header FASTA: {}", header);
+        println!("FASTA length: {} nt", genome.len());
+
+        if !pairs.is_empty() {
+            println!(
+                "In-silico PCR: ≤{} mismatches, none in the last {} bases at 3', products ≤{} bp",
+                pcr.max_mismatches, pcr.clamp, pcr.max_product
+            );
+            for pair in &pairs {
+                let products = run_pcr(pair, &genome, &pcr);
+                let names = [format!("{}_F", pair.name), format!("{}_R", pair.name)];
+                println!();
+                println!("Pair {}: {} products", pair.name, products.len());
+                println!(
+                    "{:<6} {:<10} {:<10} {:<10} {:<10} {:<9} {:<10}",
+                    "Idx", "Left", "Right", "Start", "End", "Size(bp)", "Mismatch"
+                );
+                for (i, a) in products.iter().enumerate() {
+                    println!(
+                        "{:<6} {:<10} {:<10} {:<10} {:<10} {:<9} {}/{}",
+                        i,
+                        names[a.left],
+                        names[a.right],
+                        a.start + 1,
+                        a.end,
+                        a.seq.len(),
+                        a.mismatches.0,
+                        a.mismatches.1
+                    );
+                }
+                for (i, a) in products.iter().enumerate() {
+                    println!(
+                        ">{}_{}_{} {}:{}-{} {} bp",
+                        stem,
+                        pair.name,
+                        i,
+                        header.split_whitespace().next().unwrap_or(""),
+                        a.start + 1,
+                        a.end,
+                        a.seq.len()
+                    );
+                    print_wrapped(&a.seq, 70);
+                }
+
+                let name = if paths.len() > 1 {
+                    format!("{}:{}", stem, pair.name)
+                } else {
+                    pair.name.clone()
+                };
+                let sizes: Vec<usize> = products.iter().map(|a| a.seq.len()).collect();
+                lanes.push(lane_from_sizes(&name, &sizes));
+            }
+            println!();
+            continue;
+        }
+
+        let fragments = random_fragments(&genome, &mut rng);
         println!(
-            "Frag {} preview: {}{}",
-            i,
-            preview,
-            if f.len > 40 { "..." } else { "" }
+            "No primers given; extracted {} random fragments",
+            fragments.len()
         );
+        println!();
+
+        println!(
+            "{:<6} {:<8} {:<11} {:<10}",
+            "Idx", "Start", "Length(bp)", "Dist(cm)"
+        );
+        for (i, f) in fragments.iter().enumerate() {
+            let dist = gel.migration_cm(f.len);
+            if gel.on_gel(f.len) {
+                println!("{:<6} {:<8} {:<11} {:<10.2}", i, f.start, f.len, dist);
+            } else {
+                println!("{:<6} {:<8} {:<11} ran off", i, f.start, f.len);
+            }
+        }
+
+        for (i, f) in fragments.iter().take(10).enumerate() {
+            let preview: String = f.seq.chars().take(40).collect();
+            println!(
+                "Frag {} preview: {}{}",
+                i,
+                preview,
+                if f.len > 40 { "..." } else { "" }
+            );
+        }
+        println!();
+
+        let sizes: Vec<usize> = fragments.iter().map(|f| f.len).collect();
+        lanes.push(lane_from_sizes(&stem, &sizes));
     }
+
+    println!(
+        "Gel electrophoresis (ASCII), {:.1}% agarose, {:.0} V/cm, {:.0} min:\n",
+        gel.agarose_pct, gel.volts_per_cm, gel.minutes
+    );
+    println!("{}", draw_gel(&lanes, &gel));
+
+    for path in [arg_value("--png"), arg_value("--svg")]
+        .into_iter()
+        .flatten()
+    {
+        render_gel(&lanes, &gel, &path)?;
+        println!("Gel image written to {}", path);
+    }
+
+    Ok(())
 }
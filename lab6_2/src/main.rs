@@ -1,8 +1,45 @@
+mod rank;
+
+use rank::{Resolution, Score, band_pattern, rank, score};
+use std::cmp::min;
+use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::path::Path;
-use rand::{rngs::StdRng, SeedableRng};
-use std::cmp::{min};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Topology {
+    Linear,
+    Circular,
+}
+
+impl Topology {
+    fn parse(s: &str) -> Option<Topology> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "linear" | "false" => Some(Topology::Linear),
+            "circular" | "true" => Some(Topology::Circular),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Topology::Linear => "linear",
+            Topology::Circular => "circular",
+        }
+    }
+}
 
+#[derive(Debug)]
+struct Record {
+    id: String,
+    topology: Option<Topology>,
+    seq: String,
+}
+
+/// A fragment of one record. `start` is 0-based, `end` exclusive; on a
+/// circular record the fragment that crosses the origin has `end <= start`.
 #[derive(Debug)]
 struct Fragment {
     start: usize,
@@ -10,41 +47,245 @@ struct Fragment {
     length: usize,
 }
 
-fn read_fasta_sequence<P: AsRef<Path>>(path: P) -> Result<String, String> {
+fn arg_value(name: &str) -> Option<String> {
+    let mut it = env::args().skip(1);
+    let eq = format!("{}=", name);
+    while let Some(tok) = it.next() {
+        if tok == name {
+            return it.next();
+        } else if let Some(v) = tok.strip_prefix(&eq) {
+            return Some(v.to_string());
+        }
+    }
+    None
+}
+
+fn arg_flag(name: &str) -> bool {
+    env::args().skip(1).any(|a| a == name)
+}
+
+fn arg_dir() -> String {
+    let mut it = env::args().skip(1);
+    while let Some(tok) = it.next() {
+        if tok.starts_with("--") {
+            if !tok.contains('=') && tok != "--rank" {
+                it.next();
+            }
+        } else {
+            return tok;
+        }
+    }
+    ".".to_string()
+}
+
+/// Topology from a header modifier such as `[topology=circular]`,
+/// `topology=linear` or `circular=true`.
+fn header_topology(header: &str) -> Option<Topology> {
+    header
+        .split_whitespace()
+        .map(|tok| tok.trim_matches(|c| c == '[' || c == ']'))
+        .find_map(|tok| {
+            let (key, value) = tok.split_once('=')?;
+            match key.to_ascii_lowercase().as_str() {
+                "topology" | "circular" => Topology::parse(value),
+                _ => None,
+            }
+        })
+}
+
+fn read_fasta_records<P: AsRef<Path>>(path: P) -> Result<Vec<Record>, String> {
     let content = fs::read_to_string(&path).map_err(|e| format!("cant read: {}", e))?;
-    let mut seq = String::new();
+    let mut records: Vec<Record> = Vec::new();
     for line in content.lines() {
         let line = line.trim();
-        if !line.starts_with('>') && !line.is_empty() {
-            seq.push_str(line);
+        if let Some(header) = line.strip_prefix('>') {
+            records.push(Record {
+                id: header.split_whitespace().next().unwrap_or("").to_string(),
+                topology: header_topology(header),
+                seq: String::new(),
+            });
+        } else if !line.is_empty() {
+            match records.last_mut() {
+                Some(r) => r.seq.push_str(&line.to_uppercase()),
+                None => return Err("sequence data before the first header".to_string()),
+            }
         }
     }
-    Ok(seq.to_uppercase())
+    Ok(records)
 }
 
-fn digest_ecori(sequence: &str) -> Vec<Fragment> {
-    let pattern = "GAATTC";
-    let mut fragments = Vec::new();
-    let mut start = 0;
-    let mut search_pos = 0;
+/// Per-contig topology overrides from a `contig<TAB>linear|circular` file.
+fn read_topology_map(path: &str) -> Result<HashMap<String, Topology>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("cant read: {}", e))?;
+    let mut map = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut cols = line.split('\t');
+        if let (Some(id), Some(t)) = (cols.next(), cols.next()) {
+            let t = Topology::parse(t).ok_or(format!("bad topology in '{}'", line))?;
+            map.insert(id.trim().to_string(), t);
+        }
+    }
+    Ok(map)
+}
+
+/// A restriction enzyme with a palindromic site; `cut` is where the top
+/// strand is cut, counted from the first base of the site. `N` in the site
+/// matches any base.
+#[derive(Debug, Clone, Copy)]
+struct Enzyme {
+    name: &'static str,
+    site: &'static str,
+    cut: usize,
+}
+
+/// Enzymes commonly used for RFLP and PFGE typing, from 4-cutters to
+/// rare 8-cutters.
+const PANEL: &[(&str, &str, usize)] = &[
+    ("EcoRI", "GAATTC", 1),
+    ("BamHI", "GGATCC", 1),
+    ("HindIII", "AAGCTT", 1),
+    ("PstI", "CTGCAG", 5),
+    ("SalI", "GTCGAC", 1),
+    ("XhoI", "CTCGAG", 1),
+    ("XbaI", "TCTAGA", 1),
+    ("SpeI", "ACTAGT", 1),
+    ("NheI", "GCTAGC", 1),
+    ("KpnI", "GGTACC", 5),
+    ("SacI", "GAGCTC", 5),
+    ("SmaI", "CCCGGG", 3),
+    ("ApaI", "GGGCCC", 5),
+    ("EcoRV", "GATATC", 3),
+    ("BglII", "AGATCT", 1),
+    ("NcoI", "CCATGG", 1),
+    ("NdeI", "CATATG", 2),
+    ("ClaI", "ATCGAT", 2),
+    ("AvrII", "CCTAGG", 1),
+    ("NotI", "GCGGCCGC", 2),
+    ("AscI", "GGCGCGCC", 2),
+    ("SfiI", "GGCCNNNNNGGCC", 8),
+    ("PacI", "TTAATTAA", 5),
+    ("SwaI", "ATTTAAAT", 4),
+    ("PmeI", "GTTTAAAC", 4),
+    ("HaeIII", "GGCC", 2),
+    ("AluI", "AGCT", 2),
+    ("MspI", "CCGG", 1),
+    ("TaqI", "TCGA", 1),
+    ("HhaI", "GCGC", 3),
+];
+
+fn panel() -> Vec<Enzyme> {
+    PANEL
+        .iter()
+        .map(|&(name, site, cut)| Enzyme { name, site, cut })
+        .collect()
+}
+
+fn find_enzyme(name: &str) -> Result<Enzyme, String> {
+    panel()
+        .into_iter()
+        .find(|e| e.name.eq_ignore_ascii_case(name.trim()))
+        .ok_or(format!("unknown enzyme: {}", name.trim()))
+}
+
+/// Enzyme names separated by `sep`, e.g. `EcoRI+BamHI` or `NotI,SfiI`.
+fn parse_enzymes(list: &str, sep: char) -> Result<Vec<Enzyme>, String> {
+    list.split(sep)
+        .filter(|n| !n.trim().is_empty())
+        .map(find_enzyme)
+        .collect()
+}
+
+/// Cut positions of `enzyme`. On a circular record sites spanning the
+/// origin are found too, and positions are taken modulo the length.
+fn find_cuts(sequence: &str, enzyme: &Enzyme, topology: Topology) -> Vec<usize> {
+    let site = enzyme.site.as_bytes();
+    let n = sequence.len();
+    let haystack = match topology {
+        Topology::Circular if n > 0 => {
+            let wrap = min(site.len() - 1, n);
+            format!("{}{}", sequence, &sequence[..wrap])
+        }
+        _ => sequence.to_string(),
+    };
 
-    while let Some(pos) = sequence[search_pos..].find(pattern) {
-        let cut_site = search_pos + pos + 1; // G^AATTC
-        fragments.push(Fragment {
-            start,
-            end: cut_site,
-            length: cut_site - start,
-        });
-        start = cut_site;
-        search_pos = cut_site;
+    let mut cuts = Vec::new();
+    for (i, window) in haystack.as_bytes().windows(site.len()).enumerate() {
+        if i >= n {
+            break;
+        }
+        if window.iter().zip(site).all(|(&b, &s)| s == b'N' || b == s) {
+            cuts.push((i + enzyme.cut) % n);
+        }
     }
+    cuts.sort_unstable();
+    cuts.dedup();
+    cuts
+}
+
+/// Cuts of several enzymes acting together, sorted.
+fn find_cuts_multi(sequence: &str, enzymes: &[Enzyme], topology: Topology) -> Vec<usize> {
+    let mut cuts: Vec<usize> = enzymes
+        .iter()
+        .flat_map(|e| find_cuts(sequence, e, topology))
+        .collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    cuts
+}
+
+/// Fragments between sorted cut positions of a record of length `n`.
+fn fragments_from_cuts(n: usize, cuts: &[usize], topology: Topology) -> Vec<Fragment> {
+    let mut fragments = Vec::new();
 
-    if start < sequence.len() {
-        fragments.push(Fragment {
-            start,
-            end: sequence.len(),
-            length: sequence.len() - start,
-        });
+    match topology {
+        Topology::Linear => {
+            let mut start = 0;
+            for &cut in cuts {
+                if cut > start {
+                    fragments.push(Fragment {
+                        start,
+                        end: cut,
+                        length: cut - start,
+                    });
+                }
+                start = cut;
+            }
+            if start < n {
+                fragments.push(Fragment {
+                    start,
+                    end: n,
+                    length: n - start,
+                });
+            }
+        }
+        Topology::Circular => {
+            if cuts.is_empty() {
+                // Uncut circle: one molecule of the full length.
+                fragments.push(Fragment {
+                    start: 0,
+                    end: n,
+                    length: n,
+                });
+                return fragments;
+            }
+            for w in cuts.windows(2) {
+                fragments.push(Fragment {
+                    start: w[0],
+                    end: w[1],
+                    length: w[1] - w[0],
+                });
+            }
+            let (first, last) = (cuts[0], cuts[cuts.len() - 1]);
+            fragments.push(Fragment {
+                start: last,
+                end: if first == 0 { n } else { first },
+                length: n - last + first,
+            });
+        }
     }
 
     fragments
@@ -67,12 +308,16 @@ fn draw_gel(fragments: &[Fragment], title: &str) -> String {
     let gel_width = 30usize;
     let mut canvas = vec![vec![' '; gel_width]; gel_height];
 
-    for y in 0..gel_height {
-        canvas[y][0] = '|';
-        canvas[y][gel_width - 1] = '|';
+    for row in canvas.iter_mut() {
+        row[0] = '|';
+        row[gel_width - 1] = '|';
     }
-    for x in 0..gel_width {
-        canvas[0][x] = if x == 0 || x == gel_width - 1 { '+' } else { '-' };
+    for (x, cell) in canvas[0].iter_mut().enumerate() {
+        *cell = if x == 0 || x == gel_width - 1 {
+            '+'
+        } else {
+            '-'
+        };
     }
 
     let min_bp = fragments.iter().map(|f| f.length).min().unwrap_or(100);
@@ -83,8 +328,8 @@ fn draw_gel(fragments: &[Fragment], title: &str) -> String {
             gel_height - 1,
             len_to_position(frag.length, min_bp, max_bp, gel_height),
         );
-        for x in 2..gel_width - 2 {
-            canvas[y][x] = '=';
+        for cell in &mut canvas[y][2..gel_width - 2] {
+            *cell = '=';
         }
     }
 
@@ -98,11 +343,154 @@ fn draw_gel(fragments: &[Fragment], title: &str) -> String {
     out
 }
 
-fn main() {
-    let mut rng = StdRng::seed_from_u64(42);
+/// Merges two sorted cut lists.
+fn merge_cuts(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut cuts: Vec<usize> = a.iter().chain(b).copied().collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    cuts
+}
+
+/// Ranks every panel enzyme and every pair of them by how well their band
+/// patterns tell the genomes apart.
+fn rank_panel(genomes: &[(String, Vec<(Record, Topology)>)]) -> Result<(), String> {
+    let enzymes = match arg_value("--panel") {
+        Some(list) => parse_enzymes(&list, ',')?,
+        None => panel(),
+    };
+    let parse = |name: &str, default: f64| -> Result<f64, String> {
+        match arg_value(name) {
+            Some(v) => v.parse().map_err(|_| format!("{} expects a number", name)),
+            None => Ok(default),
+        }
+    };
+    let resolution = Resolution {
+        tolerance: parse("--tolerance", 2.0)? / 100.0,
+        min_bp: parse("--min-band", 100.0)? as usize,
+        max_bp: parse("--max-band", 50_000.0)? as usize,
+    };
+    let top = parse("--top", 20.0)? as usize;
+    let out_path = arg_value("--rank-out").unwrap_or_else(|| "ranking.tsv".to_string());
+
+    // cuts[genome][enzyme][record]
+    let cuts: Vec<Vec<Vec<Vec<usize>>>> = genomes
+        .iter()
+        .map(|(_, records)| {
+            enzymes
+                .iter()
+                .map(|e| {
+                    records
+                        .iter()
+                        .map(|(r, t)| find_cuts(&r.seq, e, *t))
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut combos: Vec<Vec<usize>> = (0..enzymes.len()).map(|i| vec![i]).collect();
+    for i in 0..enzymes.len() {
+        for j in i + 1..enzymes.len() {
+            combos.push(vec![i, j]);
+        }
+    }
+
+    let mut ranking: Vec<Score> = combos
+        .iter()
+        .map(|combo| {
+            let patterns: Vec<Vec<usize>> = genomes
+                .iter()
+                .enumerate()
+                .map(|(g, (_, records))| {
+                    let lengths: Vec<usize> = records
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(r, (record, topology))| {
+                            let record_cuts = combo
+                                .iter()
+                                .fold(Vec::new(), |acc, &e| merge_cuts(&acc, &cuts[g][e][r]));
+                            fragments_from_cuts(record.seq.len(), &record_cuts, *topology)
+                        })
+                        .map(|f| f.length)
+                        .collect();
+                    band_pattern(&lengths, &resolution)
+                })
+                .collect();
+            let name = combo
+                .iter()
+                .map(|&e| enzymes[e].name)
+                .collect::<Vec<_>>()
+                .join("+");
+            score(&name, &patterns, resolution.tolerance)
+        })
+        .collect();
+    rank(&mut ranking);
+
+    println!(
+        "Ranking {} digests of {} genomes (tolerance {}%, bands {}-{} bp)\n",
+        ranking.len(),
+        genomes.len(),
+        resolution.tolerance * 100.0,
+        resolution.min_bp,
+        resolution.max_bp
+    );
+    println!(
+        "{:<5} {:<16} {:>6} {:>7} {:>9} {:>10} {:>10}",
+        "Rank", "Enzymes", "Types", "D", "MeanDice", "MeanBands", "BandRange"
+    );
+    for (i, s) in ranking.iter().take(top).enumerate() {
+        println!(
+            "{:<5} {:<16} {:>6} {:>7.3} {:>9.3} {:>10.1} {:>10}",
+            i + 1,
+            s.enzymes,
+            s.types,
+            s.index,
+            s.mean_dice,
+            s.mean_bands,
+            format!("{}-{}", s.min_bands, s.max_bands)
+        );
+    }
+
+    let mut table = fs::File::create(&out_path).map_err(|e| format!("cant write: {}", e))?;
+    let write_err = |e: std::io::Error| format!("cant write: {}", e);
+    writeln!(
+        table,
+        "rank\tenzymes\ttypes\tdiscrimination_index\tmean_dice\tmean_bands\tmin_bands\tmax_bands"
+    )
+    .map_err(write_err)?;
+    for (i, s) in ranking.iter().enumerate() {
+        writeln!(
+            table,
+            "{}\t{}\t{}\t{:.4}\t{:.4}\t{:.2}\t{}\t{}",
+            i + 1,
+            s.enzymes,
+            s.types,
+            s.index,
+            s.mean_dice,
+            s.mean_bands,
+            s.min_bands,
+            s.max_bands
+        )
+        .map_err(write_err)?;
+    }
+    println!("\nRanking written to {}", out_path);
+    Ok(())
+}
+
+fn main() -> Result<(), String> {
+    let dir = arg_dir();
+    let out_path = arg_value("--out").unwrap_or_else(|| "fragments.tsv".to_string());
+    let default_topology = match arg_value("--default-topology") {
+        Some(t) => Topology::parse(&t).ok_or("--default-topology is linear or circular")?,
+        None => Topology::Linear,
+    };
+    let overrides = match arg_value("--topology") {
+        Some(path) => read_topology_map(&path)?,
+        None => HashMap::new(),
+    };
 
-    let paths = fs::read_dir(".").expect("cant read current dir");
-    let fasta_files: Vec<_> = paths
+    let paths = fs::read_dir(&dir).map_err(|e| format!("cant read {}: {}", dir, e))?;
+    let mut fasta_files: Vec<PathBuf> = paths
         .filter_map(|p| {
             let path = p.ok()?.path();
             if path.extension()? == "fna" {
@@ -112,22 +500,71 @@ fn main() {
             }
         })
         .collect();
+    fasta_files.sort();
 
-
+    let mut genomes = Vec::new();
     for file in fasta_files {
         let name = file.file_name().unwrap().to_string_lossy().to_string();
+        let records: Vec<(Record, Topology)> = read_fasta_records(&file)?
+            .into_iter()
+            .map(|record| {
+                let topology = overrides
+                    .get(&record.id)
+                    .copied()
+                    .or(record.topology)
+                    .unwrap_or(default_topology);
+                (record, topology)
+            })
+            .collect();
+        genomes.push((name, records));
+    }
+
+    if arg_flag("--rank") {
+        return rank_panel(&genomes);
+    }
+
+    let enzymes = parse_enzymes(&arg_value("--enzyme").unwrap_or("EcoRI".to_string()), '+')?;
+    let mut table = fs::File::create(&out_path).map_err(|e| format!("cant write: {}", e))?;
+    let write_err = |e: std::io::Error| format!("cant write: {}", e);
+    writeln!(table, "file\tcontig\ttopology\tstart\tend\tlength").map_err(write_err)?;
+
+    for (name, records) in &genomes {
         println!("\nAnalyze: {}", name);
 
-        let seq = read_fasta_sequence(&file).expect("cant read sequence");
-        let fragments = digest_ecori(&seq);
+        let mut all_fragments = Vec::new();
+        for (record, topology) in records {
+            let cuts = find_cuts_multi(&record.seq, &enzymes, *topology);
+            let fragments = fragments_from_cuts(record.seq.len(), &cuts, *topology);
 
-        println!("Number of fragments: {}", fragments.len());
-        println!(
-            "Fragment lengths: {:?}",
-            fragments.iter().map(|f| f.length).collect::<Vec<_>>()
-        );
+            println!(
+                "  {} ({}, {} bp): {} fragments {:?}",
+                record.id,
+                topology.as_str(),
+                record.seq.len(),
+                fragments.len(),
+                fragments.iter().map(|f| f.length).collect::<Vec<_>>()
+            );
+            for f in &fragments {
+                writeln!(
+                    table,
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    name,
+                    record.id,
+                    topology.as_str(),
+                    f.start + 1,
+                    f.end,
+                    f.length
+                )
+                .map_err(write_err)?;
+            }
+            all_fragments.extend(fragments);
+        }
 
-        let gel = draw_gel(&fragments, &name);
+        println!("Number of fragments: {}", all_fragments.len());
+        let gel = draw_gel(&all_fragments, name);
         println!("{}", gel);
     }
+
+    println!("Fragment table written to {}", out_path);
+    Ok(())
 }
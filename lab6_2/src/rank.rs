@@ -0,0 +1,130 @@
+/// What a gel can resolve: fragments outside `min_bp..=max_bp` run off or
+/// are too faint, and bands closer than `tolerance` (a fraction of the size)
+/// look like one.
+pub struct Resolution {
+    pub tolerance: f64,
+    pub min_bp: usize,
+    pub max_bp: usize,
+}
+
+/// How well one digest tells the genomes apart.
+#[derive(Debug)]
+pub struct Score {
+    pub enzymes: String,
+    /// Groups of genomes whose patterns cannot be told apart.
+    pub types: usize,
+    /// Hunter-Gaston discriminatory index over those groups.
+    pub index: f64,
+    /// Mean pairwise Dice similarity of the patterns.
+    pub mean_dice: f64,
+    pub mean_bands: f64,
+    pub min_bands: usize,
+    pub max_bands: usize,
+}
+
+fn same_band(a: usize, b: usize, tolerance: f64) -> bool {
+    a.abs_diff(b) as f64 <= tolerance * a.max(b) as f64
+}
+
+/// Visible bands, sorted from the largest, with co-migrating fragments merged.
+pub fn band_pattern(lengths: &[usize], resolution: &Resolution) -> Vec<usize> {
+    let mut sizes: Vec<usize> = lengths
+        .iter()
+        .copied()
+        .filter(|l| (resolution.min_bp..=resolution.max_bp).contains(l))
+        .collect();
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    let mut bands: Vec<usize> = Vec::new();
+    for size in sizes {
+        match bands.last() {
+            Some(&last) if same_band(last, size, resolution.tolerance) => {}
+            _ => bands.push(size),
+        }
+    }
+    bands
+}
+
+/// Bands of `a` and `b` paired within the tolerance, walking both patterns
+/// from the top of the gel.
+fn matched_bands(a: &[usize], b: &[usize], tolerance: f64) -> usize {
+    let (mut i, mut j, mut matched) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        if same_band(a[i], b[j], tolerance) {
+            matched += 1;
+            i += 1;
+            j += 1;
+        } else if a[i] > b[j] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matched
+}
+
+pub fn dice(a: &[usize], b: &[usize], tolerance: f64) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    2.0 * matched_bands(a, b, tolerance) as f64 / (a.len() + b.len()) as f64
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    parent[i] = root;
+    root
+}
+
+pub fn score(enzymes: &str, patterns: &[Vec<usize>], tolerance: f64) -> Score {
+    let n = patterns.len();
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut dice_sum = 0.0;
+    for i in 0..n {
+        for j in i + 1..n {
+            let d = dice(&patterns[i], &patterns[j], tolerance);
+            dice_sum += d;
+            if d >= 1.0 {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut group_sizes = vec![0usize; n];
+    for i in 0..n {
+        let root = find(&mut parent, i);
+        group_sizes[root] += 1;
+    }
+    let types = group_sizes.iter().filter(|&&s| s > 0).count();
+    let index = if n < 2 {
+        0.0
+    } else {
+        let same: usize = group_sizes.iter().map(|&s| s * s.saturating_sub(1)).sum();
+        1.0 - same as f64 / (n * (n - 1)) as f64
+    };
+
+    let bands: Vec<usize> = patterns.iter().map(Vec::len).collect();
+    Score {
+        enzymes: enzymes.to_string(),
+        types,
+        index,
+        mean_dice: dice_sum / (n * n.saturating_sub(1) / 2).max(1) as f64,
+        mean_bands: bands.iter().sum::<usize>() as f64 / n.max(1) as f64,
+        min_bands: bands.iter().copied().min().unwrap_or(0),
+        max_bands: bands.iter().copied().max().unwrap_or(0),
+    }
+}
+
+/// Best first: highest discriminatory index, then the least similar
+/// patterns, then the fewest bands to read.
+pub fn rank(scores: &mut [Score]) {
+    scores.sort_by(|a, b| {
+        b.index
+            .total_cmp(&a.index)
+            .then(a.mean_dice.total_cmp(&b.mean_dice))
+            .then(a.mean_bands.total_cmp(&b.mean_bands))
+    });
+}
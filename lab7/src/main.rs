@@ -1,14 +1,16 @@
+mod annotation;
+mod ssr;
+mod trf;
+
+use annotation::{Feature, write_bed, write_gff3, write_soft_masked};
+use plotters::prelude::*;
+use ssr::{Genome, LocusParams, find_loci, write_table};
 use std::env;
 use std::fs;
 use std::io::{self, Read};
-
-#[derive(Debug)]
-struct TandemRepeat {
-    start: usize,
-    k: usize,
-    motif: String,
-    repeats: usize,
-}
+use trf::{
+    MotifClass, TandemRepeat, TrfParams, canonical_motif, find_tandem_repeats, motif_classes,
+};
 
 fn extract_sequence(raw: &str) -> String {
     let mut seq = String::new();
@@ -28,58 +30,69 @@ fn extract_sequence(raw: &str) -> String {
     cleaned
 }
 
-fn read_sequence_from_path(path: &str) -> io::Result<String> {
-    Ok(extract_sequence(&fs::read_to_string(path)?))
+/// Records of a FASTA text; a bare sequence without header becomes `seq`.
+fn parse_records(raw: &str) -> Vec<(String, String)> {
+    let mut records: Vec<(String, String)> = Vec::new();
+    for line in raw.lines() {
+        if let Some(header) = line.strip_prefix('>') {
+            let id = header.split_whitespace().next().unwrap_or("").to_string();
+            records.push((id, String::new()));
+        } else {
+            if records.is_empty() {
+                records.push(("seq".to_string(), String::new()));
+            }
+            if let Some((_, seq)) = records.last_mut() {
+                seq.push_str(&extract_sequence(line));
+            }
+        }
+    }
+    records
+}
+
+fn read_genome(path: &str) -> io::Result<Genome> {
+    Ok(Genome {
+        name: file_stem_from_arg(path),
+        records: parse_records(&fs::read_to_string(path)?),
+    })
 }
 
-fn read_sequence_from_stdin() -> io::Result<String> {
+fn read_records_from_stdin() -> io::Result<Vec<(String, String)>> {
     let mut raw = String::new();
     io::stdin().read_to_string(&mut raw)?;
-    Ok(extract_sequence(&raw))
+    Ok(parse_records(&raw))
 }
 
-fn find_tandem_repeats(seq: &str, k_min: usize, k_max: usize) -> Vec<TandemRepeat> {
-    let b = seq.as_bytes();
-    let n = b.len();
-    let mut hits: Vec<TandemRepeat> = Vec::new();
-
-    let mut i = 0usize;
-    while i + k_min * 2 <= n {
-        let mut advanced = false;
-
-        for k in k_min..=k_max {
-            if i + 2 * k > n {
-                break;
-            }
-
-            let motif = &b[i..i + k];
-
-            let mut r = 1usize;
-            while i + k * (r + 1) <= n && &b[i + k * r..i + k * (r + 1)] == motif {
-                r += 1;
-            }
-
-            if r >= 2 {
-                hits.push(TandemRepeat {
-                    start: i,
-                    k,
-                    motif: String::from_utf8(motif.to_vec()).unwrap(),
-                    repeats: r,
-                });
-                i += k * r;
-                advanced = true;
-                break;
-            }
-        }
-
-        if !advanced {
-            i += 1;
-        }
-    }
-
-    hits
+/// GFF3/BED features for the repeats found in each record.
+fn repeat_features(hits: &[(String, TandemRepeat)]) -> Vec<Feature> {
+    hits.iter()
+        .map(|(seqid, tr)| Feature {
+            seqid: seqid.clone(),
+            start: tr.start,
+            end: tr.end,
+            kind: if tr.period <= 6 {
+                "microsatellite"
+            } else {
+                "tandem_repeat"
+            },
+            name: if tr.period <= 10 {
+                format!("({}){:.1}", tr.consensus, tr.copies)
+            } else {
+                format!("period{}x{:.1}", tr.period, tr.copies)
+            },
+            score: tr.score as f64,
+            strand: '+',
+            parts: vec![(tr.start, tr.end)],
+            attributes: vec![
+                ("motif", tr.consensus.clone()),
+                ("motif_class", canonical_motif(&tr.consensus)),
+                ("period", tr.period.to_string()),
+                ("copies", format!("{:.1}", tr.copies)),
+                ("percent_match", format!("{:.1}", tr.percent_match)),
+                ("percent_indel", format!("{:.1}", tr.percent_indel)),
+            ],
+        })
+        .collect()
 }
-use plotters::prelude::*;
 
 fn plot_histogram_png(
     filename: &str,
@@ -87,8 +100,6 @@ fn plot_histogram_png(
     x_labels: &[String],
     values: &[u32],
 ) -> Result<(), Box<dyn std::error::Error>> {
-    use plotters::prelude::*;
-
     let n = x_labels.len();
     let root = BitMapBackend::new(filename, (1000, 600)).into_drawing_area();
     root.fill(&WHITE)?;
@@ -131,76 +142,108 @@ fn plot_histogram_png(
         Rectangle::new([(x0, 0u32), (x1, v)], BLUE.filled())
     }))?;
 
-    chart.draw_series(
-        (0..n)
-            .filter(|&i| values[i] > 0)
-            .map(|i| {
-                let v = values[i];
-                let xm = i as i32; 
-                Text::new(v.to_string(), (xm, v + (ymax / 50).max(1)), ("sans-serif", 14))
-            }),
-    )?;
+    chart.draw_series((0..n).filter(|&i| values[i] > 0).map(|i| {
+        let v = values[i];
+        let xm = i as i32;
+        Text::new(
+            v.to_string(),
+            (xm, v + (ymax / 50).max(1)),
+            ("sans-serif", 14),
+        )
+    }))?;
 
     root.present()?;
     Ok(())
 }
 
-
-fn plot_frequencies(
-    hits: &[TandemRepeat],
-) -> Result<(), Box<dyn std::error::Error>> {
+fn plot_frequencies(hits: &[TandemRepeat]) -> Result<(), Box<dyn std::error::Error>> {
     use std::collections::BTreeMap;
 
     let mut by_k: BTreeMap<usize, u32> = BTreeMap::new();
     for h in hits {
-        *by_k.entry(h.k).or_insert(0) += 1;
+        *by_k.entry(h.period).or_insert(0) += 1;
     }
     let xk: Vec<String> = by_k.keys().map(|k| k.to_string()).collect();
     let yk: Vec<u32> = by_k.values().copied().collect();
-    plot_histogram_png(
-        "repeats_by_k.png",
-        "Number of repeats per motif length k",
-        &xk,
-        &yk,
-    )?;
+    plot_histogram_png("repeats_by_k.png", "Number of repeats per period", &xk, &yk)?;
 
     let mut by_r: BTreeMap<usize, u32> = BTreeMap::new();
     for h in hits {
-        *by_r.entry(h.repeats).or_insert(0) += 1;
+        *by_r.entry(h.copies as usize).or_insert(0) += 1;
     }
     let xr: Vec<String> = by_r.keys().map(|r| r.to_string()).collect();
     let yr: Vec<u32> = by_r.values().copied().collect();
     plot_histogram_png(
         "repeats_by_repeats.png",
-        "Number of tandem repeats per multiplicity r",
+        "Number of tandem repeats per whole copy number r",
         &xr,
         &yr,
     )?;
-    
+
+    let classes = motif_classes(hits);
+    let top: Vec<&MotifClass> = classes.iter().take(20).collect();
+    let xc: Vec<String> = top.iter().map(|c| short_label(&c.class)).collect();
+    let yc: Vec<u32> = top.iter().map(|c| c.total_length as u32).collect();
+    plot_histogram_png(
+        "repeats_by_class.png",
+        "Total repeat length (bp) per motif class",
+        &xc,
+        &yc,
+    )?;
+
+    Ok(())
+}
+
+/// Motifs of long periods are cut so that chart labels stay readable.
+fn short_label(motif: &str) -> String {
+    if motif.len() > 12 {
+        format!("{}..({})", &motif[..8], motif.len())
+    } else {
+        motif.to_string()
+    }
+}
+
+fn write_class_summary(path: &str, hits: &[TandemRepeat]) -> io::Result<()> {
+    use std::io::Write;
+
+    let mut out = fs::File::create(path)?;
+    writeln!(
+        out,
+        "class\tperiod\trepeats\ttotal_length\tmean_copies\tmotifs"
+    )?;
+    for c in motif_classes(hits) {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{:.2}\t{}",
+            c.class,
+            c.class.len(),
+            c.count,
+            c.total_length,
+            c.total_copies / c.count as f64,
+            c.members.iter().cloned().collect::<Vec<_>>().join(",")
+        )?;
+    }
     Ok(())
 }
 
 fn plot_motif_barchart_r3(
-    out_basename: &str,   
-    display_name: &str,   
+    out_basename: &str,
+    display_name: &str,
     hits: &[TandemRepeat],
 ) -> Result<(), Box<dyn std::error::Error>> {
-    use std::collections::HashMap;
-    use plotters::prelude::*;
-    // Numără DOAR motivele cu r = 3
-    let mut count_by_motif: HashMap<String, u32> = HashMap::new();
-    for h in hits.iter().filter(|h| h.repeats == 3) {
-        *count_by_motif.entry(h.motif.clone()).or_insert(0) += 1;
-    }
-    if count_by_motif.is_empty() {
+    // Numără DOAR motivele cu r = 3, grupate pe clase canonice
+    let mut items: Vec<(String, u32)> =
+        motif_classes(hits.iter().filter(|h| h.copies as usize == 3))
+            .into_iter()
+            .map(|c| (c.class, c.count as u32))
+            .collect();
+    if items.is_empty() {
         return Ok(());
     }
-
-    let mut items: Vec<(String, u32)> = count_by_motif.into_iter().collect();
     items.sort_by_key(|(_, c)| std::cmp::Reverse(*c));
 
-    let labels: Vec<String> = items.iter().map(|(m, _)| m.clone()).collect();
-    let values: Vec<u32>   = items.iter().map(|(_, c)| *c).collect();
+    let labels: Vec<String> = items.iter().map(|(m, _)| short_label(m)).collect();
+    let values: Vec<u32> = items.iter().map(|(_, c)| *c).collect();
     let xmax = values.iter().copied().max().unwrap_or(1);
 
     let h = 120 + (labels.len() as u32) * 24;
@@ -210,7 +253,10 @@ fn plot_motif_barchart_r3(
     root.fill(&WHITE)?;
 
     let mut chart = ChartBuilder::on(&root)
-        .caption(format!("Tandem repeats r = 3 in {}", display_name), ("sans-serif", 28))
+        .caption(
+            format!("Tandem repeats r = 3 in {}", display_name),
+            ("sans-serif", 28),
+        )
         .margin(20)
         .x_label_area_size(50)
         .y_label_area_size(240)
@@ -224,7 +270,7 @@ fn plot_motif_barchart_r3(
             labels[idx].clone()
         })
         .x_desc("Frequency")
-        .y_desc("Motif (3–10 bases, r = 3)")
+        .y_desc("Motif class (r = 3)")
         .label_style(("sans-serif", 14))
         .axis_desc_style(("sans-serif", 18))
         .draw()?;
@@ -256,40 +302,185 @@ fn file_stem_from_arg(arg: &str) -> String {
         .collect()
 }
 
+fn arg_value(args: &[String], name: &str) -> Option<String> {
+    let eq = format!("{}=", name);
+    let mut it = args.iter().skip(1);
+    while let Some(tok) = it.next() {
+        if tok == name {
+            return it.next().cloned();
+        } else if let Some(v) = tok.strip_prefix(&eq) {
+            return Some(v.to_string());
+        }
+    }
+    None
+}
+
+fn positionals(args: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut it = args.iter().skip(1);
+    while let Some(tok) = it.next() {
+        if tok.starts_with("--") {
+            if !tok.contains('=') {
+                it.next();
+            }
+        } else {
+            out.push(tok.clone());
+        }
+    }
+    out
+}
+
+fn fasta_path(args: &[String]) -> Option<String> {
+    positionals(args).into_iter().next()
+}
+
+fn arg_number<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> io::Result<T> {
+    match arg_value(args, name) {
+        Some(v) => v.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} expects a number", name),
+            )
+        }),
+        None => Ok(default),
+    }
+}
+
+/// `lab7 loci [g1.fna ...]`: SSR loci across genomes, by default the ten
+/// shipped with the lab.
+fn run_loci(args: &[String]) -> io::Result<()> {
+    let mut paths = positionals(args);
+    if paths.is_empty() {
+        paths = (1..=10).map(|i| format!("g{}.fna", i)).collect();
+    }
+    let genomes = paths
+        .iter()
+        .map(|p| read_genome(p))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let trf = TrfParams {
+        min_score: arg_number(args, "--min-score", 20)?,
+        min_period: arg_number(args, "--min-period", 1)?,
+        max_period: arg_number(args, "--max-period", 6)?,
+        ..TrfParams::default()
+    };
+    let params = LocusParams {
+        flank: arg_number(args, "--flank", 20)?,
+        max_mismatches: arg_number(args, "--flank-mismatches", 3)?,
+        max_allele: arg_number(args, "--max-allele", 300)?,
+    };
+    if params.flank == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--flank must be at least 1",
+        ));
+    }
+    let out = arg_value(args, "--out").unwrap_or_else(|| "ssr_loci.tsv".to_string());
+    let top = arg_number(args, "--top", 20)?;
+
+    let loci = find_loci(&genomes, &trf, &params);
+    write_table(&out, &genomes, &loci)?;
+
+    let polymorphic = loci.iter().filter(|l| l.allele_count() > 1).count();
+    println!(
+        "{} SSR loci in {} genomes, {} polymorphic\n",
+        loci.len(),
+        genomes.len(),
+        polymorphic
+    );
+
+    let mut ranked: Vec<(usize, &ssr::Locus)> = loci.iter().enumerate().collect();
+    ranked.sort_by(|a, b| {
+        b.1.diversity()
+            .total_cmp(&a.1.diversity())
+            .then(b.1.typed().cmp(&a.1.typed()))
+    });
+    print!("{:<7} {:<8} {:<28}", "Locus", "Motif", "Origin");
+    for g in &genomes {
+        print!(" {:>5}", g.name);
+    }
+    println!(" {:>7} {:>5} {:>5}", "Alleles", "He", "PIC");
+    for (i, locus) in ranked.iter().take(top) {
+        print!(
+            "{:<7} {:<8} {:<28}",
+            format!("SSR{}", i + 1),
+            locus.motif,
+            locus.origin
+        );
+        for g in 0..genomes.len() {
+            match locus.copies(g) {
+                Some(c) => print!(" {:>5.1}", c),
+                None => print!(" {:>5}", "-"),
+            }
+        }
+        println!(
+            " {:>7} {:>5.2} {:>5.2}",
+            locus.allele_count(),
+            locus.diversity(),
+            locus.pic()
+        );
+    }
+    println!("\nLocus table written to {}", out);
+    Ok(())
+}
 
 fn main() -> std::io::Result<()> {
-    use std::env;
     let args: Vec<String> = env::args().collect();
+    if args.get(1).is_some_and(|a| a == "loci") {
+        return run_loci(&args[1..]);
+    }
+    let path = fasta_path(&args);
+
+    let defaults = TrfParams::default();
+    let params = TrfParams {
+        match_score: arg_number(&args, "--match", defaults.match_score)?,
+        mismatch: -arg_number(&args, "--mismatch", -defaults.mismatch)?.abs(),
+        indel: -arg_number(&args, "--indel", -defaults.indel)?.abs(),
+        min_score: arg_number(&args, "--min-score", defaults.min_score)?,
+        min_period: arg_number(&args, "--min-period", defaults.min_period)?,
+        max_period: arg_number(&args, "--max-period", defaults.max_period)?,
+    };
 
-    const K_MIN: usize = 3;
-    const K_MAX: usize = 10;
-
-    let seq = if args.len() > 1 {
-        read_sequence_from_path(&args[1])?
-    } else {
-        read_sequence_from_stdin()?
+    let records = match &path {
+        Some(p) => read_genome(p)?.records,
+        None => read_records_from_stdin()?,
     };
 
-    
-    let hits = find_tandem_repeats(&seq, K_MIN, K_MAX);
-    
-    let basename = if args.len() > 1 {
-    file_stem_from_arg(&args[1])
-} else {
-    "seq".to_string()
-};
+    let located: Vec<(String, TandemRepeat)> = records
+        .iter()
+        .flat_map(|(id, seq)| {
+            find_tandem_repeats(seq, &params)
+                .into_iter()
+                .map(move |tr| (id.clone(), tr))
+        })
+        .collect();
+    let hits: Vec<TandemRepeat> = located.iter().map(|(_, tr)| tr.clone()).collect();
 
-if let Err(e) = plot_motif_barchart_r3(&basename, &basename, &hits) {
-    eprintln!("could not plot r=3 motif chart for {}: {}", basename, e);
-}
+    let basename = match &path {
+        Some(p) => file_stem_from_arg(p),
+        None => "seq".to_string(),
+    };
 
+    if let Err(e) = plot_motif_barchart_r3(&basename, &basename, &hits) {
+        eprintln!("could not plot r=3 motif chart for {}: {}", basename, e);
+    }
 
-    println!("start ,k, seq, repeats, total_len");
-    for tr in find_tandem_repeats(&seq, K_MIN, K_MAX) {
-        let total_len = tr.k * tr.repeats;
+    println!(
+        "{:<14} {:<9} {:<9} {:>6} {:>7} {:>7} {:>7} {:>6}  Consensus",
+        "Record", "Start", "End", "Period", "Copies", "%Match", "%Indel", "Score"
+    );
+    for (id, tr) in &located {
         println!(
-            "start: {}, k: {}, sequence: {}, repeats: {}, total_len: {}",
-            tr.start, tr.k, tr.motif, tr.repeats, total_len
+            "{:<14} {:<9} {:<9} {:>6} {:>7.1} {:>7.1} {:>7.1} {:>6}  {}",
+            id,
+            tr.start + 1,
+            tr.end,
+            tr.period,
+            tr.copies,
+            tr.percent_match,
+            tr.percent_indel,
+            tr.score,
+            tr.consensus
         );
     }
 
@@ -297,5 +488,24 @@ if let Err(e) = plot_motif_barchart_r3(&basename, &basename, &hits) {
         println!("could not generate plots, {e}");
     }
 
+    let summary =
+        arg_value(&args, "--summary").unwrap_or_else(|| format!("{}_motif_classes.tsv", basename));
+    write_class_summary(&summary, &hits)?;
+    println!("Motif class summary written to {}", summary);
+
+    let features = repeat_features(&located);
+    if let Some(out) = arg_value(&args, "--bed") {
+        write_bed(&out, &features)?;
+        println!("BED written to {}", out);
+    }
+    if let Some(out) = arg_value(&args, "--gff") {
+        write_gff3(&out, "lab7", &features)?;
+        println!("GFF3 written to {}", out);
+    }
+    if let Some(out) = arg_value(&args, "--masked") {
+        write_soft_masked(&out, &records, &features)?;
+        println!("Soft-masked FASTA written to {}", out);
+    }
+
     Ok(())
 }
@@ -0,0 +1,287 @@
+use crate::trf::{TrfParams, find_tandem_repeats};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+
+/// A genome as named records, e.g. the segments of an influenza genome.
+pub struct Genome {
+    pub name: String,
+    pub records: Vec<(String, String)>,
+}
+
+/// Where a locus sits in one genome: the bases between its two flanks.
+#[derive(Debug, Clone)]
+pub struct Allele {
+    pub record: String,
+    pub start: usize,
+    pub length: usize,
+}
+
+/// An SSR locus, anchored by the sequence on either side of the repeat in
+/// the genome where it was first found.
+#[derive(Debug)]
+pub struct Locus {
+    pub motif: String,
+    pub period: usize,
+    pub origin: String,
+    pub left: Vec<u8>,
+    pub right: Vec<u8>,
+    /// One entry per genome; `None` where the flanks are missing or ambiguous.
+    pub alleles: Vec<Option<Allele>>,
+}
+
+impl Locus {
+    pub fn copies(&self, genome: usize) -> Option<f64> {
+        self.alleles[genome]
+            .as_ref()
+            .map(|a| a.length as f64 / self.period as f64)
+    }
+
+    fn allele_frequencies(&self) -> Vec<f64> {
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for a in self.alleles.iter().flatten() {
+            *counts.entry(a.length).or_insert(0) += 1;
+        }
+        let n: usize = counts.values().sum();
+        counts.values().map(|&c| c as f64 / n as f64).collect()
+    }
+
+    /// Distinct repeat lengths among the genomes that have the locus.
+    pub fn allele_count(&self) -> usize {
+        self.allele_frequencies().len()
+    }
+
+    pub fn typed(&self) -> usize {
+        self.alleles.iter().flatten().count()
+    }
+
+    /// Nei's unbiased gene diversity, the expected heterozygosity of a
+    /// population with these allele frequencies.
+    pub fn diversity(&self) -> f64 {
+        let n = self.typed() as f64;
+        if n < 2.0 {
+            return 0.0;
+        }
+        let homozygosity: f64 = self.allele_frequencies().iter().map(|p| p * p).sum();
+        n / (n - 1.0) * (1.0 - homozygosity)
+    }
+
+    /// Polymorphism information content (Botstein et al. 1980).
+    pub fn pic(&self) -> f64 {
+        let p = self.allele_frequencies();
+        let squares: f64 = p.iter().map(|x| x * x).sum();
+        let mut pairs = 0.0;
+        for i in 0..p.len() {
+            for j in i + 1..p.len() {
+                pairs += 2.0 * p[i] * p[i] * p[j] * p[j];
+            }
+        }
+        1.0 - squares - pairs
+    }
+}
+
+pub struct LocusParams {
+    pub flank: usize,
+    pub max_mismatches: usize,
+    /// Longest repeat tract accepted between two flanks.
+    pub max_allele: usize,
+}
+
+fn revcomp(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|&b| match b {
+            b'A' => b'T',
+            b'T' => b'A',
+            b'C' => b'G',
+            b'G' => b'C',
+            other => other,
+        })
+        .collect()
+}
+
+/// Seed index of one record. With at most `m` mismatches in a flank, one
+/// of `m + 1` disjoint seeds in it matches exactly.
+struct RecordIndex<'a> {
+    seq: &'a [u8],
+    seed: usize,
+    kmers: HashMap<&'a [u8], Vec<usize>>,
+}
+
+impl<'a> RecordIndex<'a> {
+    fn new(seq: &'a [u8], seed: usize) -> RecordIndex<'a> {
+        let mut kmers: HashMap<&[u8], Vec<usize>> = HashMap::new();
+        for (i, w) in seq.windows(seed).enumerate() {
+            kmers.entry(w).or_default().push(i);
+        }
+        RecordIndex { seq, seed, kmers }
+    }
+
+    /// Start positions where `flank` occurs with at most `max_mm` mismatches.
+    fn find(&self, flank: &[u8], max_mm: usize) -> Vec<usize> {
+        let mut hits = Vec::new();
+        if flank.len() < self.seed {
+            return hits;
+        }
+        for offset in (0..=flank.len() - self.seed).step_by(self.seed) {
+            let Some(positions) = self.kmers.get(&flank[offset..offset + self.seed]) else {
+                continue;
+            };
+            for &pos in positions {
+                let Some(start) = pos.checked_sub(offset) else {
+                    continue;
+                };
+                let Some(window) = self.seq.get(start..start + flank.len()) else {
+                    continue;
+                };
+                let mm = window.iter().zip(flank).filter(|(a, b)| a != b).count();
+                if mm <= max_mm {
+                    hits.push(start);
+                }
+            }
+        }
+        hits.sort_unstable();
+        hits.dedup();
+        hits
+    }
+}
+
+/// The tracts between `left` and `right` in one record, on either strand,
+/// as `(start, length)`.
+fn locate(
+    index: &RecordIndex,
+    left: &[u8],
+    right: &[u8],
+    params: &LocusParams,
+) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let rc_left = revcomp(right);
+    let rc_right = revcomp(left);
+    for (l, r) in [(left, right), (&rc_left[..], &rc_right[..])] {
+        let rights = index.find(r, params.max_mismatches);
+        for p in index.find(l, params.max_mismatches) {
+            let start = p + l.len();
+            if let Some(&q) = rights
+                .iter()
+                .find(|&&q| q >= start && q - start <= params.max_allele)
+            {
+                found.push((start, q - start));
+            }
+        }
+    }
+    found
+}
+
+/// Finds the SSRs of every genome and anchors each by its flanks in all the
+/// others. A repeat already covered by the allele of a known locus is not
+/// made into a new one.
+pub fn find_loci(genomes: &[Genome], trf: &TrfParams, params: &LocusParams) -> Vec<Locus> {
+    // At least 4 bases to keep the index selective, but never longer than
+    // the flank it is cut from.
+    let seed = (params.flank / (params.max_mismatches + 1))
+        .max(4)
+        .clamp(1, params.flank.max(1));
+    let indexes: Vec<Vec<RecordIndex>> = genomes
+        .iter()
+        .map(|g| {
+            g.records
+                .iter()
+                .map(|(_, s)| RecordIndex::new(s.as_bytes(), seed))
+                .collect()
+        })
+        .collect();
+
+    let mut loci: Vec<Locus> = Vec::new();
+    for (gi, genome) in genomes.iter().enumerate() {
+        for (ri, (record, seq)) in genome.records.iter().enumerate() {
+            let bytes = seq.as_bytes();
+            for tr in find_tandem_repeats(seq, trf) {
+                if tr.start < params.flank || tr.end + params.flank > bytes.len() {
+                    continue;
+                }
+                let covered = loci.iter().any(|l| {
+                    l.alleles[gi].as_ref().is_some_and(|a| {
+                        a.record == *record && a.start < tr.end && tr.start < a.start + a.length
+                    })
+                });
+                if covered {
+                    continue;
+                }
+
+                let left = bytes[tr.start - params.flank..tr.start].to_vec();
+                let right = bytes[tr.end..tr.end + params.flank].to_vec();
+                let alleles: Vec<Option<Allele>> = genomes
+                    .iter()
+                    .zip(&indexes)
+                    .map(|(g, idx)| {
+                        let hits: Vec<Allele> = g
+                            .records
+                            .iter()
+                            .zip(idx)
+                            .flat_map(|((name, _), index)| {
+                                locate(index, &left, &right, params).into_iter().map(
+                                    |(start, length)| Allele {
+                                        record: name.clone(),
+                                        start,
+                                        length,
+                                    },
+                                )
+                            })
+                            .collect();
+                        match hits.len() {
+                            1 => hits.into_iter().next(),
+                            _ => None,
+                        }
+                    })
+                    .collect();
+                if alleles[gi].is_none() {
+                    // The flanks are repeated elsewhere in the source genome.
+                    continue;
+                }
+                loci.push(Locus {
+                    motif: tr.consensus.clone(),
+                    period: tr.period,
+                    origin: format!("{}:{}:{}", genome.name, genome.records[ri].0, tr.start + 1),
+                    left,
+                    right,
+                    alleles,
+                });
+            }
+        }
+    }
+    loci
+}
+
+pub fn write_table(path: &str, genomes: &[Genome], loci: &[Locus]) -> std::io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    let names: Vec<&str> = genomes.iter().map(|g| g.name.as_str()).collect();
+    writeln!(
+        out,
+        "locus\tmotif\tperiod\torigin\t{}\ttyped\talleles\tdiversity\tpic\tleft_flank\tright_flank",
+        names.join("\t")
+    )?;
+    for (i, locus) in loci.iter().enumerate() {
+        let copies: Vec<String> = (0..genomes.len())
+            .map(|g| match locus.copies(g) {
+                Some(c) => format!("{:.1}", c),
+                None => "NA".to_string(),
+            })
+            .collect();
+        writeln!(
+            out,
+            "SSR{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.4}\t{:.4}\t{}\t{}",
+            i + 1,
+            locus.motif,
+            locus.period,
+            locus.origin,
+            copies.join("\t"),
+            locus.typed(),
+            locus.allele_count(),
+            locus.diversity(),
+            locus.pic(),
+            String::from_utf8_lossy(&locus.left),
+            String::from_utf8_lossy(&locus.right)
+        )?;
+    }
+    Ok(())
+}
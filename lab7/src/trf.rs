@@ -0,0 +1,461 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+/// Alignment weights and search limits. The defaults are the ones usually
+/// given to TRF: match +2, mismatch -7, indel -7, minimum score 50.
+#[derive(Debug, Clone)]
+pub struct TrfParams {
+    pub match_score: i32,
+    pub mismatch: i32,
+    pub indel: i32,
+    pub min_score: i32,
+    pub min_period: usize,
+    pub max_period: usize,
+}
+
+impl Default for TrfParams {
+    fn default() -> Self {
+        TrfParams {
+            match_score: 2,
+            mismatch: -7,
+            indel: -7,
+            min_score: 50,
+            min_period: 1,
+            max_period: 500,
+        }
+    }
+}
+
+/// An approximate tandem repeat over `start..end` (0-based, end exclusive).
+#[derive(Debug, Clone)]
+pub struct TandemRepeat {
+    pub start: usize,
+    pub end: usize,
+    pub period: usize,
+    pub copies: f64,
+    pub consensus: String,
+    pub percent_match: f64,
+    pub percent_indel: f64,
+    pub score: i32,
+}
+
+/// Fewer copies than this are not reported, as in TRF.
+const MIN_COPIES: f64 = 1.9;
+/// An extension stops once its score falls this far below the best so far.
+const X_DROP: i32 = 35;
+/// Longest stretch aligned in one direction from a candidate.
+const MAX_EXTENSION: usize = 50_000;
+/// Expected identity between copies, used to set the k-tuple thresholds.
+const EXPECTED_IDENTITY: f64 = 0.8;
+
+/// k-tuple length used to spot candidates with period `d`: short tuples for
+/// microsatellites, longer ones so that long periods are not swamped by
+/// chance matches.
+fn tuple_size(d: usize) -> usize {
+    match d {
+        0..=24 => 3,
+        25..=100 => 5,
+        _ => 7,
+    }
+}
+
+fn base_code(b: u8) -> Option<usize> {
+    match b {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    }
+}
+
+fn tuple_code(seq: &[u8], i: usize, k: usize) -> Option<usize> {
+    seq.get(i..i + k)?
+        .iter()
+        .try_fold(0usize, |acc, &b| Some(acc * 4 + base_code(b)?))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    /// Text base aligned to a pattern column, equal or not.
+    Aligned(usize),
+    /// Text base with no pattern column.
+    Insertion,
+    /// Pattern column skipped in the text.
+    Deletion,
+}
+
+struct Extension {
+    score: i32,
+    consumed: usize,
+    ops: Vec<Op>,
+}
+
+/// Wraparound dynamic programming: aligns `text(0), text(1), ...` against
+/// the pattern repeated without end, starting at pattern column 0, and
+/// returns the best-scoring prefix.
+fn extend(text: impl Fn(usize) -> Option<u8>, pattern: &[u8], p: &TrfParams) -> Extension {
+    const NEG: i32 = i32::MIN / 4;
+    const DIAG: u8 = 0;
+    const UP: u8 = 1;
+    const LEFT: u8 = 2;
+
+    let d = pattern.len();
+    let mut prev = vec![NEG; d];
+    prev[d - 1] = 0;
+    let mut trace: Vec<u8> = Vec::new();
+    let (mut best, mut best_row, mut best_col) = (0, 0, d - 1);
+
+    let mut row = 0;
+    while row < MAX_EXTENSION {
+        let Some(t) = text(row) else { break };
+        let mut cur = vec![NEG; d];
+        let mut tb = vec![DIAG; d];
+        for j in 0..d {
+            let sub = if t == pattern[j] && t != b'N' {
+                p.match_score
+            } else {
+                p.mismatch
+            };
+            let diag = prev[(j + d - 1) % d] + sub;
+            let up = prev[j] + p.indel;
+            (cur[j], tb[j]) = if diag >= up { (diag, DIAG) } else { (up, UP) };
+        }
+        // Deletions run along the row and may wrap past the last column.
+        for step in 0..2 * d {
+            let j = step % d;
+            let left = cur[(j + d - 1) % d] + p.indel;
+            if left > cur[j] {
+                cur[j] = left;
+                tb[j] = LEFT;
+            }
+        }
+        trace.extend_from_slice(&tb);
+        row += 1;
+
+        let (col, &row_best) = cur.iter().enumerate().max_by_key(|(_, v)| **v).unwrap();
+        if row_best > best {
+            (best, best_row, best_col) = (row_best, row, col);
+        }
+        if row_best < best - X_DROP {
+            break;
+        }
+        prev = cur;
+    }
+
+    let mut ops = Vec::new();
+    let (mut r, mut j) = (best_row, best_col);
+    while r > 0 {
+        match trace[(r - 1) * d + j] {
+            DIAG => {
+                ops.push(Op::Aligned(j));
+                r -= 1;
+                j = (j + d - 1) % d;
+            }
+            UP => {
+                ops.push(Op::Insertion);
+                r -= 1;
+            }
+            _ => {
+                ops.push(Op::Deletion);
+                j = (j + d - 1) % d;
+            }
+        }
+    }
+    ops.reverse();
+    Extension {
+        score: best,
+        consumed: best_row,
+        ops,
+    }
+}
+
+/// Aligns the region around `seed` against `pattern`, extending both ways.
+/// Returns the start, the end and the ops in text order, with pattern
+/// columns in the coordinates of `pattern`.
+fn align_both_ways(
+    seq: &[u8],
+    seed: usize,
+    pattern: &[u8],
+    p: &TrfParams,
+) -> (usize, usize, i32, Vec<(Option<u8>, Op)>) {
+    let d = pattern.len();
+    let right = extend(|i| seq.get(seed + i).copied(), pattern, p);
+    let reversed: Vec<u8> = pattern.iter().rev().copied().collect();
+    let left = extend(|i| (i < seed).then(|| seq[seed - 1 - i]), &reversed, p);
+
+    let start = seed - left.consumed;
+    let mut ops = Vec::new();
+    let mut pos = start;
+    for op in left.ops.iter().rev() {
+        let op = match *op {
+            Op::Aligned(j) => Op::Aligned(d - 1 - j),
+            other => other,
+        };
+        let base = match op {
+            Op::Deletion => None,
+            _ => {
+                pos += 1;
+                Some(seq[pos - 1])
+            }
+        };
+        ops.push((base, op));
+    }
+    for &op in &right.ops {
+        let base = match op {
+            Op::Deletion => None,
+            _ => {
+                pos += 1;
+                Some(seq[pos - 1])
+            }
+        };
+        ops.push((base, op));
+    }
+    (start, seed + right.consumed, left.score + right.score, ops)
+}
+
+/// Majority base of each pattern column.
+fn consensus(pattern: &[u8], ops: &[(Option<u8>, Op)]) -> Vec<u8> {
+    let mut counts = vec![[0usize; 4]; pattern.len()];
+    for (base, op) in ops {
+        if let (Some(b), Op::Aligned(j)) = (base, op)
+            && let Some(c) = base_code(*b)
+        {
+            counts[*j][c] += 1;
+        }
+    }
+    counts
+        .iter()
+        .zip(pattern)
+        .map(|(c, &original)| {
+            let (best, &n) = c.iter().enumerate().max_by_key(|(_, n)| **n).unwrap();
+            if n == 0 { original } else { b"ACGT"[best] }
+        })
+        .collect()
+}
+
+/// Smallest period `q` dividing the length of `motif` such that the motif
+/// is (almost) `q`-periodic.
+fn reduced_period(motif: &[u8]) -> usize {
+    let d = motif.len();
+    (1..d)
+        .filter(|&q| d.is_multiple_of(q))
+        .find(|&q| {
+            let differ = (q..d).filter(|&i| motif[i] != motif[i - q]).count();
+            differ * 10 <= d
+        })
+        .unwrap_or(d)
+}
+
+/// Scores the repeat at `seed` with period `d`, realigning once against the
+/// consensus of the first pass.
+fn evaluate(seq: &[u8], seed: usize, d: usize, p: &TrfParams) -> Option<TandemRepeat> {
+    let first = &seq[seed..seed + d];
+    let (_, _, _, ops) = align_both_ways(seq, seed, first, p);
+    let motif = consensus(first, &ops);
+    if reduced_period(&motif) < d {
+        return None;
+    }
+    let (start, end, score, ops) = align_both_ways(seq, seed, &motif, p);
+    let motif = consensus(&motif, &ops);
+
+    let copies = (end - start) as f64 / d as f64;
+    if score < p.min_score || copies < MIN_COPIES {
+        return None;
+    }
+    let (mut matches, mut indels) = (0, 0);
+    for (base, op) in &ops {
+        match (base, op) {
+            (Some(b), Op::Aligned(j)) if *b == motif[*j] => matches += 1,
+            (_, Op::Aligned(_)) => {}
+            _ => indels += 1,
+        }
+    }
+    let columns = ops.len().max(1) as f64;
+    Some(TandemRepeat {
+        start,
+        end,
+        period: d,
+        copies,
+        consensus: String::from_utf8_lossy(&motif).to_string(),
+        percent_match: 100.0 * matches as f64 / columns,
+        percent_indel: 100.0 * indels as f64 / columns,
+        score,
+    })
+}
+
+/// TRF-style search. Matching k-tuples `d` bases apart are counted over a
+/// sliding window of `d` positions; when the count reaches what a repeat of
+/// period `d` with ~80% identity would give, the region is aligned by
+/// wraparound DP against its own first copy, which tolerates substitutions
+/// and indels between copies.
+pub fn find_tandem_repeats(seq: &str, p: &TrfParams) -> Vec<TandemRepeat> {
+    let seq = seq.as_bytes();
+    let n = seq.len();
+    let max_d = p.max_period.min(n / 2);
+    if p.min_period == 0 || p.min_period > max_d {
+        return Vec::new();
+    }
+
+    let ks = [3usize, 5, 7];
+    let mut last_seen: Vec<Vec<Vec<usize>>> =
+        ks.iter().map(|&k| vec![Vec::new(); 1 << (2 * k)]).collect();
+    let mut window: Vec<VecDeque<usize>> = vec![VecDeque::new(); max_d + 1];
+    let mut covered_until = vec![0usize; max_d + 1];
+    let threshold = |d: usize| -> usize {
+        let k = tuple_size(d);
+        let expected = d.max(k) as f64 * EXPECTED_IDENTITY.powi(k as i32);
+        ((expected * 0.5) as usize).max(2)
+    };
+
+    let mut found = Vec::new();
+    for i in 0..n {
+        for (ki, &k) in ks.iter().enumerate() {
+            let Some(code) = tuple_code(seq, i, k) else {
+                continue;
+            };
+            let occurrences = &mut last_seen[ki][code];
+            for &prev in occurrences.iter().rev() {
+                let d = i - prev;
+                if d > max_d {
+                    break;
+                }
+                if d < p.min_period || tuple_size(d) != k {
+                    continue;
+                }
+                let w = &mut window[d];
+                w.push_back(i);
+                while w.front().is_some_and(|&f| f + d.max(k) <= i) {
+                    w.pop_front();
+                }
+                if w.len() >= threshold(d) && i >= covered_until[d] {
+                    let seed = i - d;
+                    match evaluate(seq, seed, d, p) {
+                        Some(tr) => {
+                            for m in (d..=max_d).step_by(d) {
+                                covered_until[m] = covered_until[m].max(tr.end);
+                            }
+                            found.push(tr);
+                        }
+                        None => covered_until[d] = i + 1,
+                    }
+                }
+            }
+            occurrences.push(i);
+            if occurrences.len() > 64 && i - occurrences[0] > max_d {
+                occurrences.retain(|&o| i - o <= max_d);
+            }
+        }
+    }
+    remove_redundant(found)
+}
+
+/// Repeats covering mostly the same bases are one locus reported at several
+/// periods (multiples of the true one, or periods shifted by indels). Of
+/// each such group the shortest period scoring within 10% of the best is
+/// kept, or the best-scoring period within 10% of that one.
+fn remove_redundant(mut found: Vec<TandemRepeat>) -> Vec<TandemRepeat> {
+    found.sort_by(|a, b| b.score.cmp(&a.score).then(a.period.cmp(&b.period)));
+    let mut assigned = vec![false; found.len()];
+    let mut kept: Vec<TandemRepeat> = Vec::new();
+    for i in 0..found.len() {
+        if assigned[i] {
+            continue;
+        }
+        let seed = &found[i];
+        let group: Vec<usize> = (i..found.len())
+            .filter(|&j| {
+                let other = &found[j];
+                let overlap = seed
+                    .end
+                    .min(other.end)
+                    .saturating_sub(seed.start.max(other.start));
+                let longer = (seed.end - seed.start).max(other.end - other.start);
+                !assigned[j] && overlap * 2 > longer
+            })
+            .collect();
+        let qualifying: Vec<usize> = group
+            .iter()
+            .copied()
+            .filter(|&j| found[j].score * 10 >= seed.score * 9)
+            .collect();
+        let shortest = qualifying.iter().map(|&j| found[j].period).min().unwrap();
+        let chosen = qualifying
+            .iter()
+            .copied()
+            .filter(|&j| found[j].period * 10 <= shortest * 11)
+            .max_by_key(|&j| (found[j].score, std::cmp::Reverse(found[j].period)))
+            .unwrap();
+        for &j in &group {
+            assigned[j] = true;
+        }
+        kept.push(found[chosen].clone());
+    }
+    kept.sort_by_key(|t| (t.start, t.period));
+    kept
+}
+
+fn revcomp(motif: &str) -> String {
+    motif
+        .bytes()
+        .rev()
+        .map(|b| match b {
+            b'A' => 'T',
+            b'T' => 'A',
+            b'C' => 'G',
+            b'G' => 'C',
+            other => other as char,
+        })
+        .collect()
+}
+
+/// The repeat class of a motif: its lexicographically smallest rotation on
+/// either strand, so `AC`, `CA`, `GT` and `TG` are all `AC`.
+pub fn canonical_motif(motif: &str) -> String {
+    let rc = revcomp(motif);
+    (0..motif.len())
+        .flat_map(|i| {
+            [
+                format!("{}{}", &motif[i..], &motif[..i]),
+                format!("{}{}", &rc[i..], &rc[..i]),
+            ]
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Repeats sharing a canonical motif.
+#[derive(Debug)]
+pub struct MotifClass {
+    pub class: String,
+    pub count: usize,
+    pub total_length: usize,
+    pub total_copies: f64,
+    /// The consensus motifs seen for this class, as reported.
+    pub members: BTreeSet<String>,
+}
+
+/// Classes of the given repeats, by decreasing total length.
+pub fn motif_classes<'a>(repeats: impl IntoIterator<Item = &'a TandemRepeat>) -> Vec<MotifClass> {
+    let mut classes: HashMap<String, MotifClass> = HashMap::new();
+    for tr in repeats {
+        let class = canonical_motif(&tr.consensus);
+        let entry = classes.entry(class.clone()).or_insert_with(|| MotifClass {
+            class,
+            count: 0,
+            total_length: 0,
+            total_copies: 0.0,
+            members: BTreeSet::new(),
+        });
+        entry.count += 1;
+        entry.total_length += tr.end - tr.start;
+        entry.total_copies += tr.copies;
+        entry.members.insert(tr.consensus.clone());
+    }
+    let mut classes: Vec<MotifClass> = classes.into_values().collect();
+    classes.sort_by(|a, b| {
+        b.total_length
+            .cmp(&a.total_length)
+            .then(a.class.cmp(&b.class))
+    });
+    classes
+}
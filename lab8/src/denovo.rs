@@ -0,0 +1,186 @@
+use crate::myers::revcomp;
+use std::collections::HashMap;
+
+/// Size limits of a candidate element and of its hallmarks. An element is
+/// `[left TIR ... right TIR]`, with the target site duplication (TSD)
+/// directly outside it on both sides.
+pub struct DenovoParams {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub min_tir: usize,
+    pub max_tir: usize,
+    pub tir_mismatches: usize,
+    pub min_tsd: usize,
+    pub max_tsd: usize,
+    /// Minimum of `-log10` of the number of such elements expected by chance.
+    pub min_score: f64,
+}
+
+impl Default for DenovoParams {
+    fn default() -> Self {
+        DenovoParams {
+            min_length: 50,
+            max_length: 5000,
+            min_tir: 10,
+            max_tir: 100,
+            tir_mismatches: 2,
+            min_tsd: 2,
+            max_tsd: 10,
+            min_score: 2.0,
+        }
+    }
+}
+
+/// A candidate mobile element. `start` and `end` are 0-based, inclusive,
+/// and exclude the TSDs.
+#[derive(Debug, Clone)]
+pub struct Element {
+    pub start: usize,
+    pub end: usize,
+    pub tsd: String,
+    pub left_tir: String,
+    /// The right TIR as it reads on the forward strand.
+    pub right_tir: String,
+    pub tir_mismatches: usize,
+    /// Expected number of elements this good in a random sequence of the
+    /// same length.
+    pub evalue: f64,
+    pub score: f64,
+}
+
+impl Element {
+    pub fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+}
+
+fn complement(b: u8) -> u8 {
+    match b {
+        b'A' => b'T',
+        b'T' => b'A',
+        b'C' => b'G',
+        b'G' => b'C',
+        _ => b'N',
+    }
+}
+
+fn is_base(b: u8) -> bool {
+    matches!(b, b'A' | b'C' | b'G' | b'T')
+}
+
+fn ln_choose(n: usize, k: usize) -> f64 {
+    (0..k).map(|i| ((n - i) as f64 / (i + 1) as f64).ln()).sum()
+}
+
+/// The TIR from `start` inwards and from `end` inwards: its length and
+/// mismatches, cut back to the last matching pair.
+fn extend_tir(seq: &[u8], start: usize, end: usize, params: &DenovoParams) -> (usize, usize) {
+    let length = end - start + 1;
+    let limit = params.max_tir.min(length / 2);
+    let (mut best, mut best_mm, mut mm) = (0, 0, 0);
+    for t in 0..limit {
+        let (a, b) = (seq[start + t], seq[end - t]);
+        if is_base(a) && complement(a) == b {
+            best = t + 1;
+            best_mm = mm;
+        } else {
+            mm += 1;
+            if mm > params.tir_mismatches {
+                break;
+            }
+        }
+    }
+    (best, best_mm)
+}
+
+/// Longest exact direct repeat directly outside `start..=end`.
+fn find_tsd(seq: &[u8], start: usize, end: usize, params: &DenovoParams) -> usize {
+    (params.min_tsd..=params.max_tsd)
+        .rev()
+        .find(|&t| {
+            t <= start
+                && end + 1 + t <= seq.len()
+                && seq[start - t..start]
+                    .iter()
+                    .zip(&seq[end + 1..end + 1 + t])
+                    .all(|(&a, &b)| is_base(a) && a == b)
+        })
+        .unwrap_or(0)
+}
+
+/// Scans one sequence for elements bounded by terminal inverted repeats
+/// and flanked by a target site duplication. Candidates are seeded by
+/// `min_tir`-mers whose reverse complement lies within the element size
+/// range downstream; of overlapping candidates the best scoring is kept.
+pub fn find_elements(dna: &str, params: &DenovoParams) -> Vec<Element> {
+    let seq = dna.to_ascii_uppercase().into_bytes();
+    let n = seq.len();
+    let k = params.min_tir.clamp(4, 16);
+    if n < params.min_length || params.min_length < 2 * k {
+        return Vec::new();
+    }
+
+    let mut index: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (i, w) in seq.windows(k).enumerate() {
+        if w.iter().all(|&b| is_base(b)) {
+            index.entry(w).or_default().push(i);
+        }
+    }
+
+    // Chance of a given TSD/TIR pair at a given start and length, and the
+    // number of starts and lengths tried.
+    let trials = (n as f64) * (params.max_length - params.min_length + 1) as f64;
+    let evalue = |tir: usize, mm: usize, tsd: usize| -> f64 {
+        let ln_p =
+            ln_choose(tir, mm) + mm as f64 * 0.75f64.ln() + (tir - mm + tsd) as f64 * 0.25f64.ln();
+        trials * ln_p.exp()
+    };
+
+    let mut candidates: Vec<Element> = Vec::new();
+    for start in params.min_tsd..n.saturating_sub(k) {
+        let seed = &seq[start..start + k];
+        let Some(ends) = index.get(&revcomp(seed)[..]) else {
+            continue;
+        };
+        let lo = start + params.min_length - k;
+        let hi = start + params.max_length - k;
+        let from = ends.partition_point(|&p| p < lo);
+        for &p in ends[from..].iter().take_while(|&&p| p <= hi) {
+            let end = p + k - 1;
+            let (tir, mm) = extend_tir(&seq, start, end, params);
+            if tir < params.min_tir {
+                continue;
+            }
+            let tsd = find_tsd(&seq, start, end, params);
+            if tsd < params.min_tsd {
+                continue;
+            }
+            let e = evalue(tir, mm, tsd);
+            let score = -e.log10();
+            if score < params.min_score {
+                continue;
+            }
+            candidates.push(Element {
+                start,
+                end,
+                tsd: String::from_utf8_lossy(&seq[start - tsd..start]).into_owned(),
+                left_tir: String::from_utf8_lossy(&seq[start..start + tir]).into_owned(),
+                right_tir: String::from_utf8_lossy(&seq[end + 1 - tir..=end]).into_owned(),
+                tir_mismatches: mm,
+                evalue: e,
+                score,
+            });
+        }
+    }
+
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.start.cmp(&b.start)));
+    let mut kept: Vec<Element> = Vec::new();
+    for c in candidates {
+        let overlaps = kept.iter().any(|k| c.start <= k.end && k.start <= c.end);
+        if !overlaps {
+            kept.push(c);
+        }
+    }
+    kept.sort_by_key(|e| e.start);
+    kept
+}
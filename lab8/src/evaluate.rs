@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+
+/// Label of predictions that carry no type of their own; they may match a
+/// truth interval of any type.
+pub const ANY: &str = "*";
+
+/// First line of the BED files written by [`write_bed`], whose names are
+/// interval types rather than free-form feature names.
+pub const BED_HEADER: &str = "#lab8 intervals: name is the type";
+
+/// A called or true feature, 0-based and end exclusive.
+#[derive(Debug, Clone)]
+pub struct Interval {
+    pub chrom: String,
+    pub start: usize,
+    pub end: usize,
+    pub kind: String,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Counts {
+    pub truth: usize,
+    pub predicted: usize,
+    pub tp: usize,
+    pub fp: usize,
+    pub fn_: usize,
+}
+
+impl Counts {
+    pub fn precision(&self) -> f64 {
+        ratio(self.tp, self.tp + self.fp)
+    }
+
+    pub fn recall(&self) -> f64 {
+        ratio(self.tp, self.tp + self.fn_)
+    }
+
+    pub fn f1(&self) -> f64 {
+        let (p, r) = (self.precision(), self.recall());
+        if p + r == 0.0 {
+            0.0
+        } else {
+            2.0 * p * r / (p + r)
+        }
+    }
+
+    fn add(&mut self, other: &Counts) {
+        self.truth += other.truth;
+        self.predicted += other.predicted;
+        self.tp += other.tp;
+        self.fp += other.fp;
+        self.fn_ += other.fn_;
+    }
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 { 0.0 } else { a as f64 / b as f64 }
+}
+
+/// Counts per type, and over all types under `"all"`.
+pub struct Evaluation {
+    pub by_kind: BTreeMap<String, Counts>,
+    pub total: Counts,
+}
+
+/// Pairs predictions with truth intervals one to one. A pair qualifies when
+/// it is on the same sequence, of the same type (or the prediction is
+/// untyped), and both ends are within `tolerance` bases; the closest pairs
+/// are taken first. Matched truth counts as TP of its type, unmatched truth
+/// as FN and unmatched predictions as FP of their own type.
+pub fn evaluate(truth: &[Interval], predicted: &[Interval], tolerance: usize) -> Evaluation {
+    // Predictions by sequence, sorted by start, so that each truth interval
+    // only looks at those starting within the tolerance.
+    let mut by_chrom: BTreeMap<&str, Vec<(usize, usize)>> = BTreeMap::new();
+    for (pi, p) in predicted.iter().enumerate() {
+        by_chrom
+            .entry(p.chrom.as_str())
+            .or_default()
+            .push((p.start, pi));
+    }
+    for starts in by_chrom.values_mut() {
+        starts.sort_unstable();
+    }
+
+    let mut pairs: Vec<(usize, usize, usize)> = Vec::new();
+    for (ti, t) in truth.iter().enumerate() {
+        let Some(starts) = by_chrom.get(t.chrom.as_str()) else {
+            continue;
+        };
+        let from = starts.partition_point(|&(s, _)| s + tolerance < t.start);
+        for &(start, pi) in starts[from..]
+            .iter()
+            .take_while(|&&(s, _)| s <= t.start + tolerance)
+        {
+            let p = &predicted[pi];
+            if p.kind != ANY && p.kind != t.kind {
+                continue;
+            }
+            let (ds, de) = (t.start.abs_diff(start), t.end.abs_diff(p.end));
+            if de <= tolerance {
+                pairs.push((ds + de, ti, pi));
+            }
+        }
+    }
+    pairs.sort_unstable();
+
+    let mut truth_hit = vec![false; truth.len()];
+    let mut predicted_hit = vec![false; predicted.len()];
+    let mut by_kind: BTreeMap<String, Counts> = BTreeMap::new();
+    for (_, ti, pi) in pairs {
+        if truth_hit[ti] || predicted_hit[pi] {
+            continue;
+        }
+        truth_hit[ti] = true;
+        predicted_hit[pi] = true;
+        let counts = by_kind.entry(truth[ti].kind.clone()).or_default();
+        counts.tp += 1;
+        counts.predicted += 1;
+    }
+    for (t, hit) in truth.iter().zip(&truth_hit) {
+        let counts = by_kind.entry(t.kind.clone()).or_default();
+        counts.truth += 1;
+        if !hit {
+            counts.fn_ += 1;
+        }
+    }
+    for (p, hit) in predicted.iter().zip(&predicted_hit) {
+        if !hit {
+            let counts = by_kind.entry(p.kind.clone()).or_default();
+            counts.predicted += 1;
+            counts.fp += 1;
+        }
+    }
+
+    let mut total = Counts::default();
+    for counts in by_kind.values() {
+        total.add(counts);
+    }
+    Evaluation { by_kind, total }
+}
+
+pub fn print_evaluation(evaluation: &Evaluation, tolerance: usize) {
+    println!("Evaluation (ends within {} bp):", tolerance);
+    println!(
+        "{:<12} {:>6} {:>9} {:>6} {:>6} {:>6} {:>9} {:>7} {:>6}",
+        "Type", "Truth", "Predicted", "TP", "FP", "FN", "Precision", "Recall", "F1"
+    );
+    let rows = evaluation
+        .by_kind
+        .iter()
+        .map(|(k, c)| (k.as_str(), c))
+        .chain(std::iter::once(("all", &evaluation.total)));
+    for (kind, c) in rows {
+        println!(
+            "{:<12} {:>6} {:>9} {:>6} {:>6} {:>6} {:>9.3} {:>7.3} {:>6.3}",
+            kind,
+            c.truth,
+            c.predicted,
+            c.tp,
+            c.fp,
+            c.fn_,
+            c.precision(),
+            c.recall(),
+            c.f1()
+        );
+    }
+}
+
+fn invalid(path: &str, line: usize, what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}:{}: {}", path, line + 1, what),
+    )
+}
+
+fn field<T: std::str::FromStr>(cols: &[&str], i: usize, path: &str, line: usize) -> io::Result<T> {
+    cols.get(i)
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| invalid(path, line, &format!("bad or missing column {}", i + 1)))
+}
+
+/// Intervals from BED, GFF3 or BEDPE, chosen by file extension.
+///
+/// BED files written by [`write_bed`] give the type in column 4. Other BED
+/// files put free-form names there, such as `(AC)12.0` from lab7 or
+/// `IR5_sp12` from lab8_3, so their intervals get `label` instead, or
+/// [`ANY`] without one. GFF3 intervals take their type from column 3. BEDPE
+/// intervals are read from the second pair of columns, the mutated genome
+/// in `lab8 simulate` truth sets, with the type from column 11. `label` also
+/// stands in for a missing type column.
+pub fn read_intervals(path: &str, label: Option<&str>) -> io::Result<Vec<Interval>> {
+    let content = fs::read_to_string(path)?;
+    let lower = path.to_ascii_lowercase();
+    let typed_bed = content.lines().next() == Some(BED_HEADER);
+    let fallback = label.unwrap_or(ANY);
+    let mut intervals = Vec::new();
+    for (n, line) in content.lines().enumerate() {
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser")
+        {
+            continue;
+        }
+        let cols: Vec<&str> = line.split('\t').collect();
+        let interval = if lower.ends_with(".gff") || lower.ends_with(".gff3") {
+            let start: usize = field(&cols, 3, path, n)?;
+            Interval {
+                chrom: cols[0].to_string(),
+                start: start.saturating_sub(1),
+                end: field(&cols, 4, path, n)?,
+                kind: cols.get(2).unwrap_or(&fallback).to_string(),
+            }
+        } else if lower.ends_with(".bedpe") {
+            Interval {
+                chrom: cols
+                    .get(3)
+                    .ok_or_else(|| invalid(path, n, "missing column 4"))?
+                    .to_string(),
+                start: field(&cols, 4, path, n)?,
+                end: field(&cols, 5, path, n)?,
+                kind: cols.get(10).unwrap_or(&fallback).to_string(),
+            }
+        } else {
+            Interval {
+                chrom: cols[0].to_string(),
+                start: field(&cols, 1, path, n)?,
+                end: field(&cols, 2, path, n)?,
+                kind: cols
+                    .get(3)
+                    .filter(|v| typed_bed && !v.is_empty())
+                    .unwrap_or(&fallback)
+                    .to_string(),
+            }
+        };
+        intervals.push(interval);
+    }
+    Ok(intervals)
+}
+
+/// Intervals as BED, with the type as the feature name.
+pub fn write_bed(path: &str, intervals: &[Interval]) -> io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    writeln!(out, "{}", BED_HEADER)?;
+    for i in intervals {
+        writeln!(out, "{}\t{}\t{}\t{}", i.chrom, i.start, i.end, i.kind)?;
+    }
+    Ok(())
+}
@@ -1,26 +1,280 @@
-use rand::{Rng, SeedableRng};
+mod denovo;
+mod evaluate;
+mod myers;
+mod simulate;
+
+use denovo::DenovoParams;
+use evaluate::Interval;
+use myers::{Hit, find_elements};
 use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use simulate::SimParams;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
 
 const BASE_DNA_LEN: usize = 250;
 
 const TRANSPOSONS: [&str; 4] = [
-    "ATGCGTACGA",   // T1
-    "TTACGTTACG",   // T2
-    "CGTACGCGTA",   // T3
-    "GATTACAGAT",   // T4
+    "ATGCGTACGA", // T1
+    "TTACGTTACG", // T2
+    "CGTACGCGTA", // T3
+    "GATTACAGAT", // T4
 ];
 
-fn main() {
+fn arg_value(args: &[String], name: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+    args.iter().enumerate().find_map(|(i, a)| {
+        if a == name {
+            args.get(i + 1).cloned()
+        } else {
+            a.strip_prefix(&prefix).map(str::to_string)
+        }
+    })
+}
+
+fn arg_number<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> io::Result<T> {
+    match arg_value(args, name) {
+        Some(v) => v.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} expects a number", name),
+            )
+        }),
+        None => Ok(default),
+    }
+}
+
+/// Arguments that are neither flags nor flag values.
+fn positionals(args: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut skip = false;
+    for a in args.iter().skip(1) {
+        if skip {
+            skip = false;
+        } else if a.starts_with("--") {
+            skip = !a.contains('=');
+        } else {
+            out.push(a.clone());
+        }
+    }
+    out
+}
+
+/// Records as `(id, sequence)`, the id being the first word of the header.
+fn read_fasta(path: &str) -> io::Result<Vec<(String, String)>> {
+    let content = fs::read_to_string(path)?;
+    let mut records: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        if let Some(header) = line.strip_prefix('>') {
+            let id = header.split_whitespace().next().unwrap_or("").to_string();
+            records.push((id, String::new()));
+        } else {
+            if records.is_empty() {
+                records.push(("seq".to_string(), String::new()));
+            }
+            if let Some((_, seq)) = records.last_mut() {
+                seq.push_str(line.trim());
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// `lab8 denovo genome.fa ...`: candidate elements found from their TSDs
+/// and TIRs alone, without a library.
+fn run_denovo(args: &[String]) -> io::Result<()> {
+    let paths = positionals(args);
+    if paths.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: lab8 denovo <genome.fa>... [--out denovo_elements.tsv] [--bed elements.bed]",
+        ));
+    }
+    let defaults = DenovoParams::default();
+    let params = DenovoParams {
+        min_length: arg_number(args, "--min-length", defaults.min_length)?,
+        max_length: arg_number(args, "--max-length", defaults.max_length)?,
+        min_tir: arg_number(args, "--min-tir", defaults.min_tir)?,
+        max_tir: arg_number(args, "--max-tir", defaults.max_tir)?,
+        tir_mismatches: arg_number(args, "--tir-mismatches", defaults.tir_mismatches)?,
+        min_tsd: arg_number(args, "--min-tsd", defaults.min_tsd)?,
+        max_tsd: arg_number(args, "--max-tsd", defaults.max_tsd)?,
+        min_score: arg_number(args, "--min-score", defaults.min_score)?,
+    };
+    if params.min_length > params.max_length || params.min_tsd > params.max_tsd {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "minimum sizes must not exceed the maximum ones",
+        ));
+    }
+    let out = arg_value(args, "--out").unwrap_or_else(|| "denovo_elements.tsv".to_string());
+
+    let mut table = fs::File::create(&out)?;
+    writeln!(
+        table,
+        "record\tstart\tend\tlength\ttsd\tleft_tir\tright_tir\ttir_mismatches\tevalue\tscore"
+    )?;
+    println!(
+        "{:<16} {:>9} {:>9} {:>7}  {:<10} {:<24} {:>3} {:>10} {:>6}",
+        "Record", "Start", "End", "Length", "TSD", "Left TIR", "MM", "E-value", "Score"
+    );
+    let mut intervals = Vec::new();
+    for path in &paths {
+        for (id, seq) in read_fasta(path)? {
+            for e in denovo::find_elements(&seq, &params) {
+                intervals.push(Interval {
+                    chrom: id.clone(),
+                    start: e.start,
+                    end: e.end + 1,
+                    kind: "MEI".to_string(),
+                });
+                println!(
+                    "{:<16} {:>9} {:>9} {:>7}  {:<10} {:<24} {:>3} {:>10.2e} {:>6.1}",
+                    id,
+                    e.start,
+                    e.end,
+                    e.len(),
+                    e.tsd,
+                    e.left_tir,
+                    e.tir_mismatches,
+                    e.evalue,
+                    e.score
+                );
+                writeln!(
+                    table,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.3e}\t{:.2}",
+                    id,
+                    e.start,
+                    e.end,
+                    e.len(),
+                    e.tsd,
+                    e.left_tir,
+                    e.right_tir,
+                    e.tir_mismatches,
+                    e.evalue,
+                    e.score
+                )?;
+            }
+        }
+    }
+    println!(
+        "\n{} candidate elements written to {}",
+        intervals.len(),
+        out
+    );
+    if let Some(bed) = arg_value(args, "--bed") {
+        evaluate::write_bed(&bed, &intervals)?;
+    }
+    Ok(())
+}
+
+/// Elements from `--library` (FASTA), or the four `TRANSPOSONS`.
+fn read_library(args: &[String]) -> io::Result<Vec<(String, String)>> {
+    match arg_value(args, "--library") {
+        Some(lib) => read_fasta(&lib),
+        None => Ok(TRANSPOSONS
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (format!("T{}", i + 1), t.to_string()))
+            .collect()),
+    }
+}
+
+fn write_fasta(path: &str, records: &[(String, String)]) -> io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    for (id, seq) in records {
+        writeln!(out, ">{}", id)?;
+        for line in seq.as_bytes().chunks(70) {
+            out.write_all(line)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// `lab8 simulate genome.fa`: the genome with random events applied, and
+/// the events as a VCF and BEDPE truth set. Elements come from `--library`
+/// (FASTA), by default the four `TRANSPOSONS`.
+fn run_simulate(args: &[String]) -> io::Result<()> {
+    let Some(path) = positionals(args).into_iter().next() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: lab8 simulate <genome.fa> [--library elements.fa] [--seed 42]",
+        ));
+    };
+    let records = read_fasta(&path)?;
+    let library = read_library(args)?;
+
+    let defaults = SimParams::default();
+    let params = SimParams {
+        snp_rate: arg_number(args, "--snp-rate", defaults.snp_rate)?,
+        indel_rate: arg_number(args, "--indel-rate", defaults.indel_rate)?,
+        element_rate: arg_number(args, "--element-rate", defaults.element_rate)?,
+        deletion_rate: arg_number(args, "--deletion-rate", defaults.deletion_rate)?,
+        inversion_rate: arg_number(args, "--inversion-rate", defaults.inversion_rate)?,
+        duplication_rate: arg_number(args, "--duplication-rate", defaults.duplication_rate)?,
+        translocation_rate: arg_number(args, "--translocation-rate", defaults.translocation_rate)?,
+        max_indel: arg_number(args, "--max-indel", defaults.max_indel)?,
+        min_sv: arg_number(args, "--min-sv", defaults.min_sv)?,
+        max_sv: arg_number(args, "--max-sv", defaults.max_sv)?,
+        min_tsd: arg_number(args, "--min-tsd", defaults.min_tsd)?,
+        max_tsd: arg_number(args, "--max-tsd", defaults.max_tsd)?,
+    };
+    if params.max_indel == 0 || params.min_sv > params.max_sv || params.min_tsd > params.max_tsd {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "size ranges must be non-empty",
+        ));
+    }
+    let seed = arg_number(args, "--seed", 42u64)?;
+    let out = arg_value(args, "--out").unwrap_or_else(|| "mutated.fa".to_string());
+    let vcf = arg_value(args, "--vcf").unwrap_or_else(|| "truth.vcf".to_string());
+    let bedpe = arg_value(args, "--bedpe").unwrap_or_else(|| "truth.bedpe".to_string());
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (mutated, events) = simulate::simulate(&records, &library, &params, &mut rng);
+    write_fasta(&out, &mutated)?;
+    simulate::write_vcf(&vcf, &records, &events)?;
+    simulate::write_bedpe(&bedpe, &records, &events)?;
+
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for e in &events {
+        match counts.iter_mut().find(|(k, _)| *k == e.kind.name()) {
+            Some((_, c)) => *c += 1,
+            None => counts.push((e.kind.name(), 1)),
+        }
+    }
+    println!("{} events (seed {}):", events.len(), seed);
+    for (kind, count) in counts {
+        println!("  {:<7} {}", kind, count);
+    }
+    println!(
+        "Mutated genome written to {}, truth to {} and {}",
+        out, vcf, bedpe
+    );
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("denovo") => return run_denovo(&args[1..]),
+        Some("simulate") => return run_simulate(&args[1..]),
+        Some("detect") => return run_detect(&args[1..]),
+        Some("evaluate") => return run_evaluate(&args[1..]),
+        _ => {}
+    }
+    let max_distance: usize = arg_number(&args, "--max-distance", 1)?;
+    let tolerance: usize = arg_number(&args, "--tolerance", 0)?;
+
     let base_dna = generate_random_dna(BASE_DNA_LEN);
     println!("Initial sequence, length {}:", base_dna.len());
     println!("{base_dna}\n");
 
     let (dna_with_tps, inserted_positions) = insert_transposons(base_dna, &TRANSPOSONS);
 
-    println!(
-        "Final sequence, length {}:",
-        dna_with_tps.len()
-    );
+    println!("Final sequence, length {}:", dna_with_tps.len());
     println!("{dna_with_tps}\n");
 
     println!("Real positions of inserted transposons:");
@@ -29,21 +283,121 @@ fn main() {
     }
     println!();
 
-    let detected = detect_transposons(&dna_with_tps, &TRANSPOSONS);
+    let detected = detect_transposons(&dna_with_tps, &TRANSPOSONS, max_distance);
 
-    println!("Detected positions by the algorithm:");
-    for (pattern, start, end) in detected {
-        println!("{pattern}: start = {start}, end = {end}");
+    println!("Detected positions by the algorithm (at most {max_distance} edits):");
+    for hit in &detected {
+        println!(
+            "{}: start = {}, end = {}, strand = {}, distance = {}, copy = {}, alignment = {}",
+            hit.name, hit.start, hit.end, hit.strand, hit.distance, hit.copy, hit.cigar
+        );
     }
+    println!();
+
+    let truth: Vec<Interval> = inserted_positions
+        .iter()
+        .map(|(name, start, end)| Interval {
+            chrom: "demo".to_string(),
+            start: *start,
+            end: end + 1,
+            kind: name.clone(),
+        })
+        .collect();
+    let predicted = hit_intervals("demo", &detected);
+    evaluate::print_evaluation(
+        &evaluate::evaluate(&truth, &predicted, tolerance),
+        tolerance,
+    );
+    Ok(())
+}
+
+/// Hits as intervals typed by element name (the name up to the first space).
+fn hit_intervals(chrom: &str, hits: &[Hit]) -> Vec<Interval> {
+    hits.iter()
+        .map(|h| Interval {
+            chrom: chrom.to_string(),
+            start: h.start,
+            end: h.end + 1,
+            kind: h.name.split_whitespace().next().unwrap_or("").to_string(),
+        })
+        .collect()
+}
+
+/// `lab8 detect genome.fa`: copies of the library elements, by default the
+/// four `TRANSPOSONS`, on both strands of every record.
+fn run_detect(args: &[String]) -> io::Result<()> {
+    let Some(path) = positionals(args).into_iter().next() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: lab8 detect <genome.fa> [--library elements.fa] [--max-distance 1] [--bed hits.bed]",
+        ));
+    };
+    let library = read_library(args)?;
+    let max_distance: usize = arg_number(args, "--max-distance", 1)?;
+
+    let mut intervals = Vec::new();
+    for (id, seq) in read_fasta(&path)? {
+        let hits = find_elements(&seq, &library, max_distance);
+        for hit in &hits {
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                id, hit.name, hit.start, hit.end, hit.strand, hit.distance, hit.cigar
+            );
+        }
+        // Typed as the event they stand for, like `denovo` calls and the
+        // `simulate` truth sets, so that `evaluate --types` can pick them.
+        intervals.extend(hit_intervals(&id, &hits).into_iter().map(|i| Interval {
+            kind: "MEI".to_string(),
+            ..i
+        }));
+    }
+    println!(
+        "\n{} hits with at most {} edits",
+        intervals.len(),
+        max_distance
+    );
+    if let Some(out) = arg_value(args, "--bed") {
+        evaluate::write_bed(&out, &intervals)?;
+        println!("Hits written to {}", out);
+    }
+    Ok(())
+}
+
+/// `lab8 evaluate --truth truth.bedpe --predicted calls.bed`: precision,
+/// recall and F1 of any caller that writes BED or GFF3 intervals.
+fn run_evaluate(args: &[String]) -> io::Result<()> {
+    let (Some(truth_path), Some(predicted_path)) =
+        (arg_value(args, "--truth"), arg_value(args, "--predicted"))
+    else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: lab8 evaluate --truth <bed|gff3|bedpe> --predicted <bed|gff3|bedpe> \
+             [--tolerance 10] [--types MEI,DUP] [--label MEI]",
+        ));
+    };
+    let tolerance: usize = arg_number(args, "--tolerance", 10)?;
+    let label = arg_value(args, "--label");
+    let mut truth = evaluate::read_intervals(&truth_path, label.as_deref())?;
+    let mut predicted = evaluate::read_intervals(&predicted_path, label.as_deref())?;
+    if let Some(types) = arg_value(args, "--types") {
+        let types: Vec<&str> = types.split(',').map(str::trim).collect();
+        truth.retain(|i| types.contains(&i.kind.as_str()));
+        predicted.retain(|i| i.kind == evaluate::ANY || types.contains(&i.kind.as_str()));
+    }
+    evaluate::print_evaluation(
+        &evaluate::evaluate(&truth, &predicted, tolerance),
+        tolerance,
+    );
+    Ok(())
 }
 
 fn generate_random_dna(len: usize) -> String {
-    let mut rng = StdRng::seed_from_u64(42); 
+    let mut rng = StdRng::seed_from_u64(42);
     let bases = ['A', 'C', 'G', 'T'];
 
     (0..len)
         .map(|_| {
-            let idx = rng.gen_range(0..bases.len());
+            let idx = rng.random_range(0..bases.len());
             bases[idx]
         })
         .collect()
@@ -57,10 +411,10 @@ fn insert_transposons(
     let mut positions: Vec<(String, usize, usize)> = Vec::new();
 
     let planned_positions: [usize; 4] = [
-        50,   // T1
-        120,  // T2
-        55,   // T3
-        180,  // T4
+        50,  // T1
+        120, // T2
+        55,  // T3
+        180, // T4
     ];
 
     let mut offset = 0;
@@ -74,6 +428,11 @@ fn insert_transposons(
         let len_p = pattern_chars.len();
 
         seq.splice(real_pos..real_pos, pattern_chars.clone());
+        // Elements already in place at or after the insertion point move on.
+        for (_, start, end) in positions.iter_mut().filter(|(_, s, _)| *s >= real_pos) {
+            *start += len_p;
+            *end += len_p;
+        }
 
         let start = real_pos;
         let end = real_pos + len_p - 1;
@@ -87,18 +446,13 @@ fn insert_transposons(
     (final_seq, positions)
 }
 
-fn detect_transposons(
-    dna: &str,
-    transposons: &[&str],
-) -> Vec<(String, usize, usize)> {
-    let mut results = Vec::new();
-
-    for (i, &pattern) in transposons.iter().enumerate() {
-        for (start, _) in dna.match_indices(pattern) {
-            let end = start + pattern.len() - 1;
-            results.push((format!("T{} ({})", i + 1, pattern), start, end));
-        }
-    }
-
-    results
+/// Copies of the transposons on either strand, allowing up to
+/// `max_distance` substitutions, insertions or deletions.
+fn detect_transposons(dna: &str, transposons: &[&str], max_distance: usize) -> Vec<Hit> {
+    let elements: Vec<(String, String)> = transposons
+        .iter()
+        .enumerate()
+        .map(|(i, &pattern)| (format!("T{} ({})", i + 1, pattern), pattern.to_string()))
+        .collect();
+    find_elements(dna, &elements, max_distance)
 }
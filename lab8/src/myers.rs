@@ -0,0 +1,225 @@
+/// Approximate pattern search with Myers' bit-vector algorithm, in the
+/// block form of Hyyrö (one 64-bit word per 64 pattern rows), so patterns
+/// of any length are supported.
+const WORD: usize = 64;
+const HIGH: u64 = 1 << (WORD - 1);
+
+/// One approximate occurrence of an element. `start` and `end` are 0-based
+/// and inclusive, on the forward strand whatever the strand of the hit.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    pub strand: char,
+    pub distance: usize,
+    /// The genomic copy, read in the element's orientation.
+    pub copy: String,
+    /// Alignment of the copy to the element: `=` match, `X` mismatch, `I`
+    /// base only in the copy, `D` element base missing from the copy.
+    pub cigar: String,
+}
+
+pub fn revcomp(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|&b| match b {
+            b'A' => b'T',
+            b'T' => b'A',
+            b'C' => b'G',
+            b'G' => b'C',
+            other => other,
+        })
+        .collect()
+}
+
+/// Whether two bases match; anything other than ACGT matches nothing, as in
+/// the bit-vector search.
+fn same_base(a: u8, b: u8) -> bool {
+    a == b && base_index(a).is_some()
+}
+
+fn base_index(b: u8) -> Option<usize> {
+    match b.to_ascii_uppercase() {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    }
+}
+
+/// Edit distance of `pattern` against the best substring of `text` ending
+/// at each position. Bases other than ACGT match nothing.
+fn end_scores(pattern: &[u8], text: &[u8]) -> Vec<usize> {
+    let m = pattern.len();
+    let blocks = m.div_ceil(WORD);
+    // peq[c][b]: rows of block b where the pattern has base c.
+    let mut peq = vec![vec![0u64; blocks]; 5];
+    for (i, &p) in pattern.iter().enumerate() {
+        if let Some(c) = base_index(p) {
+            peq[c][i / WORD] |= 1 << (i % WORD);
+        }
+    }
+    let last = 1u64 << ((m - 1) % WORD);
+
+    let mut pv = vec![u64::MAX; blocks];
+    let mut mv = vec![0u64; blocks];
+    let mut score = m;
+    let mut scores = Vec::with_capacity(text.len());
+    for &t in text {
+        let c = base_index(t).unwrap_or(4);
+        // The top row is all zeros when searching: no carry into block 0.
+        let mut hin: i32 = 0;
+        for b in 0..blocks {
+            let hin_neg = (hin < 0) as u64;
+            let hin_pos = (hin > 0) as u64;
+            let mut eq = peq[c][b];
+            let xv = eq | mv[b];
+            eq |= hin_neg;
+            let xh = ((eq & pv[b]).wrapping_add(pv[b]) ^ pv[b]) | eq;
+            let mut ph = mv[b] | !(xh | pv[b]);
+            let mut mh = pv[b] & xh;
+
+            if b == blocks - 1 {
+                if ph & last != 0 {
+                    score += 1;
+                } else if mh & last != 0 {
+                    score -= 1;
+                }
+            }
+            hin = (ph & HIGH != 0) as i32 - (mh & HIGH != 0) as i32;
+
+            ph = (ph << 1) | hin_pos;
+            mh = (mh << 1) | hin_neg;
+            pv[b] = mh | !(xv | ph);
+            mv[b] = ph & xv;
+        }
+        scores.push(score);
+    }
+    scores
+}
+
+/// Global alignment of `pattern` to a suffix of `window` (free start in the
+/// window, fixed end), as the start offset in the window, the distance and
+/// the operations from the left.
+fn align_suffix(pattern: &[u8], window: &[u8]) -> (usize, usize, Vec<u8>) {
+    let (m, w) = (pattern.len(), window.len());
+    let mut d = vec![vec![0usize; w + 1]; m + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=m {
+        for j in 1..=w {
+            let sub = d[i - 1][j - 1] + !same_base(pattern[i - 1], window[j - 1]) as usize;
+            d[i][j] = sub.min(d[i - 1][j] + 1).min(d[i][j - 1] + 1);
+        }
+    }
+
+    let (mut i, mut j) = (m, w);
+    let mut ops = Vec::new();
+    while i > 0 {
+        if j > 0 && d[i][j] == d[i - 1][j - 1] + !same_base(pattern[i - 1], window[j - 1]) as usize
+        {
+            ops.push(if same_base(pattern[i - 1], window[j - 1]) {
+                b'='
+            } else {
+                b'X'
+            });
+            i -= 1;
+            j -= 1;
+        } else if d[i][j] == d[i - 1][j] + 1 {
+            ops.push(b'D');
+            i -= 1;
+        } else {
+            ops.push(b'I');
+            j -= 1;
+        }
+    }
+    ops.reverse();
+    (j, d[m][w], ops)
+}
+
+fn cigar(ops: &[u8]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < ops.len() {
+        let run = ops[i..].iter().take_while(|&&o| o == ops[i]).count();
+        out.push_str(&format!("{}{}", run, ops[i] as char));
+        i += run;
+    }
+    out
+}
+
+/// Occurrences of `pattern` in `text` within `max_distance` edits. Of a run
+/// of neighbouring end positions that all qualify, only the best is kept.
+fn search_strand(
+    pattern: &[u8],
+    text: &[u8],
+    max_distance: usize,
+) -> Vec<(usize, usize, usize, Vec<u8>)> {
+    let scores = end_scores(pattern, text);
+    let mut ends: Vec<usize> = Vec::new();
+    let mut j = 0;
+    while j < scores.len() {
+        if scores[j] > max_distance {
+            j += 1;
+            continue;
+        }
+        let mut best = j;
+        while j < scores.len() && scores[j] <= max_distance {
+            if scores[j] < scores[best] {
+                best = j;
+            }
+            j += 1;
+        }
+        ends.push(best);
+    }
+
+    let mut found: Vec<(usize, usize, usize, Vec<u8>)> = Vec::new();
+    for end in ends {
+        let lo = (end + 1).saturating_sub(pattern.len() + max_distance);
+        let (offset, distance, ops) = align_suffix(pattern, &text[lo..=end]);
+        let start = lo + offset;
+        // Neighbouring runs can settle on the same copy.
+        if found.last().is_some_and(|f| f.0 == start) {
+            continue;
+        }
+        found.push((start, end, distance, ops));
+    }
+    found
+}
+
+/// Approximate occurrences of every element on both strands, sorted by
+/// position.
+pub fn find_elements(dna: &str, elements: &[(String, String)], max_distance: usize) -> Vec<Hit> {
+    let text = dna.to_ascii_uppercase().into_bytes();
+    let mut hits = Vec::new();
+    for (name, element) in elements {
+        let forward = element.to_ascii_uppercase().into_bytes();
+        if forward.is_empty() || forward.len() <= max_distance {
+            continue;
+        }
+        let reverse = revcomp(&forward);
+        for (strand, pattern) in [('+', &forward), ('-', &reverse)] {
+            for (start, end, distance, mut ops) in search_strand(pattern, &text, max_distance) {
+                let mut copy = text[start..=end].to_vec();
+                if strand == '-' {
+                    copy = revcomp(&copy);
+                    ops.reverse();
+                }
+                hits.push(Hit {
+                    name: name.clone(),
+                    start,
+                    end,
+                    strand,
+                    distance,
+                    copy: String::from_utf8_lossy(&copy).into_owned(),
+                    cigar: cigar(&ops),
+                });
+            }
+        }
+    }
+    hits.sort_by(|a, b| (a.start, a.end, &a.name).cmp(&(b.start, b.end, &b.name)));
+    hits
+}
@@ -0,0 +1,481 @@
+use crate::myers::revcomp;
+use rand::Rng;
+use std::fs;
+use std::io::{self, Write};
+
+/// Per-base rates of each kind of event and the size ranges they draw from.
+pub struct SimParams {
+    pub snp_rate: f64,
+    pub indel_rate: f64,
+    pub element_rate: f64,
+    pub deletion_rate: f64,
+    pub inversion_rate: f64,
+    pub duplication_rate: f64,
+    pub translocation_rate: f64,
+    pub max_indel: usize,
+    pub min_sv: usize,
+    pub max_sv: usize,
+    pub min_tsd: usize,
+    pub max_tsd: usize,
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        SimParams {
+            snp_rate: 1e-3,
+            indel_rate: 1e-4,
+            element_rate: 2e-5,
+            deletion_rate: 1e-5,
+            inversion_rate: 1e-5,
+            duplication_rate: 1e-5,
+            translocation_rate: 5e-6,
+            max_indel: 10,
+            min_sv: 50,
+            max_sv: 1000,
+            min_tsd: 4,
+            max_tsd: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Snp,
+    Insertion,
+    Deletion,
+    /// A library element, inserted with a target site duplication.
+    Element,
+    LargeDeletion,
+    Inversion,
+    /// A tandem copy of a segment, inserted right after it.
+    Duplication,
+    /// A segment cut out and inserted elsewhere, possibly in another record.
+    Translocation,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Snp => "SNP",
+            Kind::Insertion => "INS",
+            Kind::Deletion => "DEL",
+            Kind::Element => "MEI",
+            Kind::LargeDeletion => "SV_DEL",
+            Kind::Inversion => "INV",
+            Kind::Duplication => "DUP",
+            Kind::Translocation => "TRA",
+        }
+    }
+
+    /// The VCF `SVTYPE`, which does not tell small events from large ones.
+    fn svtype(self) -> &'static str {
+        match self {
+            Kind::Insertion | Kind::Element => "INS",
+            Kind::Deletion | Kind::LargeDeletion => "DEL",
+            other => other.name(),
+        }
+    }
+}
+
+/// One simulated event, in both coordinate systems: `ref_*` on the input
+/// genome and `alt_*` on the mutated one, 0-based and end exclusive. The
+/// ref span is what was removed or changed (empty for insertions) and the
+/// alt span what was added or changed (empty for deletions), so each event
+/// also marks the point where it happened in the other genome.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub id: String,
+    pub kind: Kind,
+    pub ref_record: usize,
+    pub ref_start: usize,
+    pub ref_end: usize,
+    pub alt_record: usize,
+    pub alt_start: usize,
+    pub alt_end: usize,
+    pub strand: char,
+    pub element: Option<String>,
+    pub tsd: String,
+    /// Bases put in place of the ref span, for the events written out in
+    /// full in the VCF.
+    pub inserted: Vec<u8>,
+}
+
+/// Replace `ref_len` bases at `start` with `replacement`; `affected` is the
+/// part of the replacement that the event's alt span covers.
+struct Edit {
+    start: usize,
+    ref_len: usize,
+    replacement: Vec<u8>,
+    affected: (usize, usize),
+    event: usize,
+}
+
+/// Whether `from..=to` misses every reserved span of a record. Spans cover
+/// an event and the unchanged base before it that anchors it in the VCF.
+fn is_free(reserved: &[(usize, usize)], from: usize, to: usize) -> bool {
+    reserved.iter().all(|&(s, e)| to < s || e < from)
+}
+
+const BASES: [u8; 4] = *b"ACGT";
+
+fn random_bases(rng: &mut impl Rng, len: usize) -> Vec<u8> {
+    (0..len).map(|_| BASES[rng.random_range(0..4)]).collect()
+}
+
+/// Applies random events to `records` and returns the mutated records and
+/// the events, sorted by their position in the input.
+pub fn simulate(
+    records: &[(String, String)],
+    library: &[(String, String)],
+    params: &SimParams,
+    rng: &mut impl Rng,
+) -> (Vec<(String, String)>, Vec<Event>) {
+    let seqs: Vec<Vec<u8>> = records
+        .iter()
+        .map(|(_, s)| s.to_ascii_uppercase().into_bytes())
+        .collect();
+    let rates = [
+        (Kind::Snp, params.snp_rate),
+        (Kind::Insertion, params.indel_rate / 2.0),
+        (Kind::Deletion, params.indel_rate / 2.0),
+        (
+            Kind::Element,
+            if library.is_empty() {
+                0.0
+            } else {
+                params.element_rate
+            },
+        ),
+        (Kind::LargeDeletion, params.deletion_rate),
+        (Kind::Inversion, params.inversion_rate),
+        (Kind::Duplication, params.duplication_rate),
+        (Kind::Translocation, params.translocation_rate),
+    ];
+
+    let mut events: Vec<Event> = Vec::new();
+    let mut edits: Vec<Vec<Edit>> = seqs.iter().map(|_| Vec::new()).collect();
+    let mut reserved: Vec<Vec<(usize, usize)>> = seqs.iter().map(|_| Vec::new()).collect();
+    // Translocated segments waiting for a destination: (event, segment).
+    let mut moving: Vec<(usize, Vec<u8>)> = Vec::new();
+
+    for (r, seq) in seqs.iter().enumerate() {
+        let n = seq.len();
+        let mut free_from = 1;
+        for p in 1..n {
+            let u: f64 = rng.random();
+            let mut acc = 0.0;
+            let Some(kind) = rates.iter().find_map(|&(kind, rate)| {
+                acc += rate;
+                (u < acc).then_some(kind)
+            }) else {
+                continue;
+            };
+
+            let ref_len = match kind {
+                Kind::Snp => 1,
+                Kind::Insertion | Kind::Element => 0,
+                Kind::Deletion => rng.random_range(1..=params.max_indel),
+                _ => rng.random_range(params.min_sv..=params.max_sv),
+            };
+            let tsd_len = if kind == Kind::Element {
+                rng.random_range(params.min_tsd..=params.max_tsd)
+            } else {
+                0
+            };
+            // Keep an unchanged base on both sides, and the TSD source intact.
+            if p < free_from + tsd_len || p + ref_len + 1 >= n {
+                continue;
+            }
+
+            let index = events.len();
+            let span = &seq[p..p + ref_len];
+            let mut strand = '+';
+            let mut element = None;
+            let mut tsd = Vec::new();
+            let (replacement, affected) = match kind {
+                Kind::Snp => {
+                    let alt = loop {
+                        let b = BASES[rng.random_range(0..4)];
+                        if b != seq[p] {
+                            break b;
+                        }
+                    };
+                    (vec![alt], (0, 1))
+                }
+                Kind::Insertion => {
+                    let len = rng.random_range(1..=params.max_indel);
+                    (random_bases(rng, len), (0, len))
+                }
+                Kind::Deletion | Kind::LargeDeletion => (Vec::new(), (0, 0)),
+                Kind::Element => {
+                    let (name, body) = &library[rng.random_range(0..library.len())];
+                    let mut body = body.to_ascii_uppercase().into_bytes();
+                    if rng.random_bool(0.5) {
+                        body = revcomp(&body);
+                        strand = '-';
+                    }
+                    element = Some(name.clone());
+                    tsd = seq[p - tsd_len..p].to_vec();
+                    let len = body.len();
+                    body.extend_from_slice(&tsd);
+                    (body, (0, len))
+                }
+                Kind::Inversion => {
+                    strand = '-';
+                    (revcomp(span), (0, ref_len))
+                }
+                Kind::Duplication => {
+                    let mut twice = span.to_vec();
+                    twice.extend_from_slice(span);
+                    (twice, (ref_len, 2 * ref_len))
+                }
+                Kind::Translocation => {
+                    moving.push((index, span.to_vec()));
+                    (Vec::new(), (0, 0))
+                }
+            };
+
+            events.push(Event {
+                id: format!("sim{}", index + 1),
+                kind,
+                ref_record: r,
+                ref_start: p,
+                ref_end: p + ref_len,
+                alt_record: r,
+                alt_start: 0,
+                alt_end: 0,
+                strand,
+                element,
+                tsd: String::from_utf8_lossy(&tsd).into_owned(),
+                inserted: match kind {
+                    Kind::Snp | Kind::Insertion => replacement.clone(),
+                    _ => Vec::new(),
+                },
+            });
+            edits[r].push(Edit {
+                start: p,
+                ref_len,
+                replacement,
+                affected,
+                event: index,
+            });
+            reserved[r].push((p - 1 - tsd_len, p + ref_len));
+            free_from = p + ref_len + 1;
+        }
+    }
+
+    // Each translocated segment goes to a random free point of the genome,
+    // chosen in proportion to record length.
+    let total: usize = seqs.iter().map(Vec::len).sum();
+    for (index, segment) in moving {
+        let destination = (0..100).find_map(|_| {
+            let mut x = rng.random_range(0..total);
+            let r = seqs
+                .iter()
+                .position(|s| {
+                    if x < s.len() {
+                        true
+                    } else {
+                        x -= s.len();
+                        false
+                    }
+                })
+                .unwrap_or(0);
+            (x >= 1 && x + 1 < seqs[r].len() && is_free(&reserved[r], x - 1, x)).then_some((r, x))
+        });
+        let Some((r, q)) = destination else {
+            // No room anywhere: the segment is simply lost.
+            events[index].kind = Kind::LargeDeletion;
+            continue;
+        };
+        let len = segment.len();
+        events[index].alt_record = r;
+        edits[r].push(Edit {
+            start: q,
+            ref_len: 0,
+            replacement: segment,
+            affected: (0, len),
+            event: index,
+        });
+        reserved[r].push((q - 1, q));
+    }
+
+    let mut mutated = Vec::with_capacity(seqs.len());
+    for (r, seq) in seqs.iter().enumerate() {
+        let record_edits = &mut edits[r];
+        record_edits.sort_by_key(|e| e.start);
+        let mut out: Vec<u8> = Vec::with_capacity(seq.len());
+        let mut cursor = 0;
+        for edit in record_edits.iter() {
+            out.extend_from_slice(&seq[cursor..edit.start]);
+            let event = &mut events[edit.event];
+            // The alt span of a translocation is where its segment landed.
+            let source = event.kind == Kind::Translocation && edit.replacement.is_empty();
+            if !source {
+                let at = out.len();
+                event.alt_start = at + edit.affected.0;
+                event.alt_end = at + edit.affected.1;
+            }
+            out.extend_from_slice(&edit.replacement);
+            cursor = edit.start + edit.ref_len;
+        }
+        out.extend_from_slice(&seq[cursor..]);
+        mutated.push((
+            records[r].0.clone(),
+            String::from_utf8_lossy(&out).into_owned(),
+        ));
+    }
+
+    events.sort_by_key(|e| (e.ref_record, e.ref_start));
+    (mutated, events)
+}
+
+/// VCF 4.2 of the events against the input genome. Small events are written
+/// out in full, the others as symbolic alleles; `ALT_CHROM`, `ALT_POS` and
+/// `ALT_END` give the event's span in the mutated genome (1-based; a
+/// deletion has `ALT_END = ALT_POS - 1`).
+pub fn write_vcf(path: &str, records: &[(String, String)], events: &[Event]) -> io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    writeln!(out, "##fileformat=VCFv4.2")?;
+    writeln!(out, "##source=lab8-simulate")?;
+    for (id, seq) in records {
+        writeln!(out, "##contig=<ID={},length={}>", id, seq.len())?;
+    }
+    for line in [
+        "##ALT=<ID=DEL,Description=\"Deletion\">",
+        "##ALT=<ID=INV,Description=\"Inversion\">",
+        "##ALT=<ID=DUP:TANDEM,Description=\"Tandem duplication\">",
+        "##ALT=<ID=INS:ME,Description=\"Insertion of a library element\">",
+        "##ALT=<ID=TRA,Description=\"Segment moved to ALT_CHROM:ALT_POS\">",
+        "##INFO=<ID=SVTYPE,Number=1,Type=String,Description=\"Type of variant\">",
+        "##INFO=<ID=EVENT,Number=1,Type=String,Description=\"Simulated event type\">",
+        "##INFO=<ID=SVLEN,Number=1,Type=Integer,Description=\"Length change, or length of the segment\">",
+        "##INFO=<ID=END,Number=1,Type=Integer,Description=\"Last reference base of the event\">",
+        "##INFO=<ID=ELEMENT,Number=1,Type=String,Description=\"Inserted library element\">",
+        "##INFO=<ID=STRAND,Number=1,Type=String,Description=\"Orientation of the inserted or inverted sequence\">",
+        "##INFO=<ID=TSD,Number=1,Type=String,Description=\"Target site duplication\">",
+        "##INFO=<ID=ALT_CHROM,Number=1,Type=String,Description=\"Record of the event in the mutated genome\">",
+        "##INFO=<ID=ALT_POS,Number=1,Type=Integer,Description=\"First mutated-genome base of the event\">",
+        "##INFO=<ID=ALT_END,Number=1,Type=Integer,Description=\"Last mutated-genome base of the event\">",
+    ] {
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out, "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO")?;
+
+    for e in events {
+        let seq = records[e.ref_record].1.to_ascii_uppercase().into_bytes();
+        let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+        let anchor = e.ref_start - 1;
+        let ref_len = e.ref_end - e.ref_start;
+        let alt_len = e.alt_end - e.alt_start;
+        let (pos, reference, alternative, svlen) = match e.kind {
+            Kind::Snp => (
+                e.ref_start,
+                text(&seq[e.ref_start..e.ref_end]),
+                text(&e.inserted),
+                0,
+            ),
+            Kind::Insertion => (
+                anchor,
+                text(&seq[anchor..=anchor]),
+                text(&seq[anchor..=anchor]) + &text(&e.inserted),
+                alt_len as i64,
+            ),
+            Kind::Deletion => (
+                anchor,
+                text(&seq[anchor..e.ref_end]),
+                text(&seq[anchor..=anchor]),
+                -(ref_len as i64),
+            ),
+            Kind::LargeDeletion => (
+                anchor,
+                text(&seq[anchor..=anchor]),
+                "<DEL>".into(),
+                -(ref_len as i64),
+            ),
+            Kind::Inversion => (
+                anchor,
+                text(&seq[anchor..=anchor]),
+                "<INV>".into(),
+                ref_len as i64,
+            ),
+            Kind::Duplication => (
+                anchor,
+                text(&seq[anchor..=anchor]),
+                "<DUP:TANDEM>".into(),
+                ref_len as i64,
+            ),
+            Kind::Element => (
+                anchor,
+                text(&seq[anchor..=anchor]),
+                "<INS:ME>".into(),
+                alt_len as i64,
+            ),
+            Kind::Translocation => (
+                anchor,
+                text(&seq[anchor..=anchor]),
+                "<TRA>".into(),
+                ref_len as i64,
+            ),
+        };
+
+        let mut info = vec![
+            format!("SVTYPE={}", e.kind.svtype()),
+            format!("EVENT={}", e.kind.name()),
+        ];
+        if e.kind != Kind::Snp {
+            info.push(format!("SVLEN={}", svlen));
+            info.push(format!("END={}", e.ref_end.max(pos + 1)));
+        }
+        if let Some(element) = &e.element {
+            info.push(format!("ELEMENT={}", element));
+            info.push(format!("TSD={}", e.tsd));
+        }
+        if matches!(e.kind, Kind::Element | Kind::Inversion) {
+            info.push(format!("STRAND={}", e.strand));
+        }
+        info.push(format!("ALT_CHROM={}", records[e.alt_record].0));
+        info.push(format!("ALT_POS={}", e.alt_start + 1));
+        info.push(format!("ALT_END={}", e.alt_end));
+
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t.\tPASS\t{}",
+            records[e.ref_record].0,
+            pos + 1,
+            e.id,
+            reference,
+            alternative,
+            info.join(";")
+        )?;
+    }
+    Ok(())
+}
+
+/// BEDPE pairing each event's span in the input genome with its span in
+/// the mutated one.
+pub fn write_bedpe(path: &str, records: &[(String, String)], events: &[Event]) -> io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    writeln!(
+        out,
+        "#ref_chrom\tref_start\tref_end\talt_chrom\talt_start\talt_end\tname\tscore\tstrand1\tstrand2\ttype\telement\ttsd"
+    )?;
+    for e in events {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t.\t+\t{}\t{}\t{}\t{}",
+            records[e.ref_record].0,
+            e.ref_start,
+            e.ref_end,
+            records[e.alt_record].0,
+            e.alt_start,
+            e.alt_end,
+            e.id,
+            e.strand,
+            e.kind.name(),
+            e.element.as_deref().unwrap_or("."),
+            if e.tsd.is_empty() { "." } else { &e.tsd }
+        )?;
+    }
+    Ok(())
+}
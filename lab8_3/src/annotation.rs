@@ -0,0 +1,120 @@
+use std::fs;
+use std::io::{self, Write};
+
+/// One annotated feature. `start`/`end` are 0-based, end exclusive, like
+/// BED; GFF3 output converts to 1-based inclusive. `parts` are the bases
+/// the feature actually covers (one part for a tandem repeat).
+#[derive(Debug, Clone)]
+pub struct Feature {
+    pub seqid: String,
+    pub start: usize,
+    pub end: usize,
+    pub kind: &'static str,
+    pub name: String,
+    pub score: f64,
+    pub strand: char,
+    pub parts: Vec<(usize, usize)>,
+    pub attributes: Vec<(&'static str, String)>,
+}
+
+/// Percent-encodes the characters GFF3 reserves in attribute values.
+fn gff_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            ';' | '=' | '&' | ',' | '%' | '\t' | '\n' => {
+                out.push_str(&format!("%{:02X}", c as u32))
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// BED6, or BED12 with one block per part when any feature has several.
+/// The BED score column is the feature score clamped to 0..=1000.
+pub fn write_bed(path: &str, features: &[Feature]) -> io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    let blocks = features.iter().any(|f| f.parts.len() > 1);
+    for f in features {
+        let score = f.score.round().clamp(0.0, 1000.0) as u32;
+        write!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            f.seqid, f.start, f.end, f.name, score, f.strand
+        )?;
+        if blocks {
+            let sizes: Vec<String> = f.parts.iter().map(|(s, e)| (e - s).to_string()).collect();
+            let starts: Vec<String> = f
+                .parts
+                .iter()
+                .map(|(s, _)| (s - f.start).to_string())
+                .collect();
+            write!(
+                out,
+                "\t{}\t{}\t0\t{}\t{},\t{},",
+                f.start,
+                f.end,
+                f.parts.len(),
+                sizes.join(","),
+                starts.join(",")
+            )?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+pub fn write_gff3(path: &str, source: &str, features: &[Feature]) -> io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    writeln!(out, "##gff-version 3")?;
+    for (i, f) in features.iter().enumerate() {
+        let mut attributes = vec![
+            format!("ID={}{}", f.kind, i + 1),
+            format!("Name={}", gff_escape(&f.name)),
+        ];
+        attributes.extend(
+            f.attributes
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, gff_escape(v))),
+        );
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t.\t{}",
+            f.seqid,
+            source,
+            f.kind,
+            f.start + 1,
+            f.end,
+            f.score,
+            f.strand,
+            attributes.join(";")
+        )?;
+    }
+    Ok(())
+}
+
+/// FASTA with the bases of every feature part in lowercase.
+pub fn write_soft_masked(
+    path: &str,
+    records: &[(String, String)],
+    features: &[Feature],
+) -> io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    for (id, seq) in records {
+        let mut masked = seq.to_ascii_uppercase().into_bytes();
+        for f in features.iter().filter(|f| f.seqid == *id) {
+            for &(s, e) in &f.parts {
+                for b in &mut masked[s.min(seq.len())..e.min(seq.len())] {
+                    *b = b.to_ascii_lowercase();
+                }
+            }
+        }
+        writeln!(out, ">{}", id)?;
+        for line in masked.chunks(70) {
+            out.write_all(line)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,214 @@
+//! Nearest-neighbour free energies in kcal/mol at 37 °C, from the Turner
+//! 2004 set as distributed with ViennaRNA. DNA input is read as the RNA
+//! it would be transcribed to, so `T` stands for `U`.
+
+/// Pair types, in the order of the tables below.
+const CG: usize = 0;
+const GC: usize = 1;
+const GU: usize = 2;
+const UG: usize = 3;
+const AU: usize = 4;
+const UA: usize = 5;
+pub const PAIR_TYPES: usize = 6;
+
+/// `STACK[outer][inner]` for the outer pair `(i, j)` stacked on the inner
+/// pair `(p, q)`, the inner one read as `(q, p)` so that both are taken 5'
+/// to 3'.
+const STACK: [[f64; 6]; 6] = [
+    [-2.40, -3.30, -2.10, -1.40, -2.10, -2.10],
+    [-3.30, -3.40, -2.50, -1.50, -2.20, -2.40],
+    [-2.10, -2.50, 1.30, -0.50, -1.40, -1.30],
+    [-1.40, -1.50, -0.50, 0.30, -0.60, -1.00],
+    [-2.10, -2.20, -1.40, -0.60, -1.10, -0.90],
+    [-2.10, -2.40, -1.30, -1.00, -0.90, -1.30],
+];
+
+/// Hairpin loop initiation by loop length, 0 to 30.
+const HAIRPIN: [f64; 31] = [
+    f64::INFINITY,
+    f64::INFINITY,
+    f64::INFINITY,
+    5.4,
+    5.6,
+    5.7,
+    5.4,
+    6.0,
+    5.5,
+    6.4,
+    6.5,
+    6.6,
+    6.7,
+    6.78,
+    6.86,
+    6.94,
+    7.01,
+    7.07,
+    7.13,
+    7.19,
+    7.25,
+    7.3,
+    7.35,
+    7.4,
+    7.44,
+    7.49,
+    7.53,
+    7.57,
+    7.61,
+    7.65,
+    7.69,
+];
+
+/// Bulge loop initiation by bulge length, 0 to 6.
+const BULGE: [f64; 7] = [0.0, 3.8, 2.8, 3.2, 3.6, 4.0, 4.4];
+
+/// Interior loop initiation by the number of unpaired bases, 0 to 20. The
+/// 1x1, 1x2 and 2x2 loops use these too rather than their own tables.
+const INTERIOR: [f64; 21] = [
+    f64::INFINITY,
+    f64::INFINITY,
+    0.5,
+    1.6,
+    1.1,
+    2.0,
+    2.0,
+    2.1,
+    2.3,
+    2.4,
+    2.5,
+    2.6,
+    2.7,
+    2.8,
+    2.9,
+    2.9,
+    3.0,
+    3.1,
+    3.1,
+    3.2,
+    3.3,
+];
+
+/// Penalty per unpaired base of difference between the sides of an
+/// interior loop, and its cap.
+const NINIO: f64 = 0.6;
+const MAX_NINIO: f64 = 3.0;
+
+/// Penalty for an A-U or G-U pair closing an interior loop, standing in for
+/// the terminal mismatch tables.
+const INTERIOR_AU: f64 = 0.7;
+
+/// Largest interior loop or bulge considered, in unpaired bases.
+pub const MAX_LOOP: usize = 30;
+
+/// Multiloop penalties, `a + b * unpaired + c * branches`, as in Turner 1999.
+pub const ML_CLOSING: f64 = 3.4;
+pub const ML_BASE: f64 = 0.0;
+pub const ML_BRANCH: f64 = 0.4;
+
+/// RT at 37 °C in kcal/mol.
+pub const KT: f64 = 0.61632;
+
+/// Extrapolation of loop initiation past the tables, `1.75 RT ln(n / max)`.
+const LXC: f64 = 1.07856;
+
+/// Penalty for an A-U or G-U pair closing a helix.
+pub const TERMINAL_AU: f64 = 0.5;
+
+fn rna(b: u8) -> u8 {
+    match b.to_ascii_uppercase() {
+        b'T' => b'U',
+        other => other,
+    }
+}
+
+pub fn pair_type(a: u8, b: u8) -> Option<usize> {
+    match (rna(a), rna(b)) {
+        (b'C', b'G') => Some(CG),
+        (b'G', b'C') => Some(GC),
+        (b'G', b'U') => Some(GU),
+        (b'U', b'G') => Some(UG),
+        (b'A', b'U') => Some(AU),
+        (b'U', b'A') => Some(UA),
+        _ => None,
+    }
+}
+
+pub fn can_pair(a: u8, b: u8) -> bool {
+    pair_type(a, b).is_some()
+}
+
+pub fn is_wobble(a: u8, b: u8) -> bool {
+    matches!(pair_type(a, b), Some(GU) | Some(UG))
+}
+
+pub fn terminal_penalty(a: u8, b: u8) -> f64 {
+    match pair_type(a, b) {
+        Some(CG) | Some(GC) | None => 0.0,
+        Some(_) => TERMINAL_AU,
+    }
+}
+
+fn extrapolate(table: &[f64], len: usize) -> f64 {
+    let max = table.len() - 1;
+    if len <= max {
+        table[len]
+    } else {
+        table[max] + LXC * (len as f64 / max as f64).ln()
+    }
+}
+
+/// Initiation of a hairpin loop of `len` unpaired bases; fewer than three
+/// cannot close.
+pub fn hairpin_loop(len: usize) -> f64 {
+    extrapolate(&HAIRPIN, len)
+}
+
+/// Initiation of a bulge of `len` unpaired bases on one side of a helix.
+pub fn bulge_loop(len: usize) -> f64 {
+    extrapolate(&BULGE, len)
+}
+
+/// The hairpin closed by `(i, j)`. Loops of three take the terminal AU
+/// penalty; longer ones the bonus for a UU or GA first mismatch, which
+/// stands in for the terminal mismatch table.
+pub fn hairpin(s: &[u8], i: usize, j: usize) -> f64 {
+    let len = j - i - 1;
+    let mut e = hairpin_loop(len);
+    if len == 3 {
+        e += terminal_penalty(s[i], s[j]);
+    } else {
+        e += match (rna(s[i + 1]), rna(s[j - 1])) {
+            (b'U', b'U') => -0.9,
+            (b'G', b'A') => -0.8,
+            _ => 0.0,
+        };
+    }
+    e
+}
+
+/// The loop between the pair `(i, j)` and the pair `(k, l)` inside it with
+/// nothing else in between: a stack, a bulge or an interior loop.
+pub fn two_loop(s: &[u8], i: usize, j: usize, k: usize, l: usize) -> f64 {
+    match (pair_type(s[i], s[j]), pair_type(s[l], s[k])) {
+        (Some(outer), Some(inner)) => two_loop_by_type(outer, inner, k - i - 1, j - l - 1),
+        _ => f64::INFINITY,
+    }
+}
+
+/// [`two_loop`] by the pair types of `(i, j)` and of `(l, k)`, and the
+/// unpaired bases on either side.
+pub fn two_loop_by_type(outer: usize, inner: usize, n1: usize, n2: usize) -> f64 {
+    let au = |t: usize| if t == CG || t == GC { 0.0 } else { TERMINAL_AU };
+    match (n1, n2) {
+        (0, 0) => STACK[outer][inner],
+        // A single bulged base leaves the helix stacked.
+        (0, 1) | (1, 0) => bulge_loop(1) + STACK[outer][inner],
+        (0, n) | (n, 0) => bulge_loop(n) + au(outer) + au(inner),
+        _ => {
+            let closing = |t: usize| if t == CG || t == GC { 0.0 } else { INTERIOR_AU };
+            extrapolate(&INTERIOR, n1 + n2)
+                + (NINIO * n1.abs_diff(n2) as f64).min(MAX_NINIO)
+                + closing(outer)
+                + closing(inner)
+        }
+    }
+}
@@ -0,0 +1,443 @@
+//! Secondary structure of single RNA sequences: the most base pairs
+//! (Nussinov), the minimum free energy structure (Zuker) and base-pair
+//! probabilities (McCaskill). The last two use the nearest-neighbour model
+//! of [`crate::energy`], without dangling ends or coaxial stacking, and
+//! allow A-U, G-C and G-U pairs closing hairpins of at least three bases.
+
+use crate::energy::{
+    KT, MAX_LOOP, ML_BASE, ML_BRANCH, ML_CLOSING, PAIR_TYPES, can_pair, hairpin, pair_type,
+    terminal_penalty, two_loop_by_type,
+};
+
+/// Fewest unpaired bases in a hairpin loop.
+const MIN_HAIRPIN: usize = 3;
+
+/// Energies are folded in integer dcal/mol so that the traceback can
+/// compare them exactly.
+const INF: i32 = i32::MAX / 4;
+
+fn dcal(e: f64) -> i32 {
+    if e.is_finite() {
+        (e * 100.0).round() as i32
+    } else {
+        INF
+    }
+}
+
+/// Two-pair loop energies of a sequence by pair types and loop shape,
+/// converted once by the caller, as they are looked up for every pair
+/// inside every pair.
+struct LoopTable<T> {
+    table: Vec<T>,
+    /// Pair type of two bases by their codes, and the code of each base.
+    types: [[Option<usize>; 5]; 5],
+    codes: Vec<usize>,
+}
+
+impl<T: Copy> LoopTable<T> {
+    fn new(seq: &[u8], convert: impl Fn(f64) -> T) -> Self {
+        let side = MAX_LOOP + 1;
+        let mut table = Vec::with_capacity(PAIR_TYPES * PAIR_TYPES * side * side);
+        for outer in 0..PAIR_TYPES {
+            for inner in 0..PAIR_TYPES {
+                for n1 in 0..side {
+                    for n2 in 0..side {
+                        table.push(convert(two_loop_by_type(outer, inner, n1, n2)));
+                    }
+                }
+            }
+        }
+        const BASES: &[u8; 5] = b"ACGUN";
+        let mut types = [[None; 5]; 5];
+        for (a, &x) in BASES.iter().enumerate() {
+            for (b, &y) in BASES.iter().enumerate() {
+                types[a][b] = pair_type(x, y);
+            }
+        }
+        let codes = seq
+            .iter()
+            .map(|&b| {
+                BASES
+                    .iter()
+                    .position(|&x| x == b.to_ascii_uppercase())
+                    .unwrap_or(if b.eq_ignore_ascii_case(&b'T') { 3 } else { 4 })
+            })
+            .collect();
+        LoopTable {
+            table,
+            types,
+            codes,
+        }
+    }
+
+    /// The loop between `(i, j)` and `(k, l)`, both of which must pair and
+    /// leave at most [`MAX_LOOP`] bases on each side.
+    fn get(&self, i: usize, j: usize, k: usize, l: usize) -> T {
+        let c = &self.codes;
+        let (Some(outer), Some(inner)) = (self.types[c[i]][c[j]], self.types[c[l]][c[k]]) else {
+            unreachable!("loop between bases that do not pair");
+        };
+        let side = MAX_LOOP + 1;
+        self.table[((outer * PAIR_TYPES + inner) * side + k - i - 1) * side + j - l - 1]
+    }
+}
+
+/// A structure in dot-bracket notation and its free energy in kcal/mol.
+pub struct Fold {
+    pub structure: String,
+    pub dg: f64,
+}
+
+fn dot_bracket(n: usize, pairs: &[(usize, usize)]) -> String {
+    let mut s = vec![b'.'; n];
+    for &(i, j) in pairs {
+        s[i] = b'(';
+        s[j] = b')';
+    }
+    String::from_utf8_lossy(&s).into_owned()
+}
+
+/// Structure with the most base pairs, and their number.
+pub fn nussinov(seq: &[u8]) -> (String, usize) {
+    let n = seq.len();
+    // `m[i][j]` for the bases `i..j`, end exclusive, so that empty
+    // stretches need no special case.
+    let mut m = vec![0u32; (n + 1) * (n + 1)];
+    let at = |m: &[u32], i: usize, j: usize| m[i * (n + 1) + j];
+    for d in 1..=n {
+        for i in 0..=n - d {
+            let j = i + d;
+            let mut best = at(&m, i + 1, j);
+            for k in i + MIN_HAIRPIN + 1..j {
+                if can_pair(seq[i], seq[k]) {
+                    best = best.max(at(&m, i + 1, k) + 1 + at(&m, k + 1, j));
+                }
+            }
+            m[i * (n + 1) + j] = best;
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut todo = vec![(0, n)];
+    while let Some((i, j)) = todo.pop() {
+        if j <= i {
+            continue;
+        }
+        let best = at(&m, i, j);
+        if best == at(&m, i + 1, j) {
+            todo.push((i + 1, j));
+            continue;
+        }
+        for k in i + MIN_HAIRPIN + 1..j {
+            if can_pair(seq[i], seq[k]) && best == at(&m, i + 1, k) + 1 + at(&m, k + 1, j) {
+                pairs.push((i, k));
+                todo.push((i + 1, k));
+                todo.push((k + 1, j));
+                break;
+            }
+        }
+    }
+    (dot_bracket(n, &pairs), pairs.len())
+}
+
+/// Interior loops closed by `(i, j)` whose inner pair `(k, l)` leaves at
+/// most [`MAX_LOOP`] unpaired bases, stacks and bulges included.
+fn inner_pairs(i: usize, j: usize) -> impl Iterator<Item = (usize, usize)> {
+    let k_max = (i + 1 + MAX_LOOP).min(j.saturating_sub(MIN_HAIRPIN + 2));
+    (i + 1..=k_max).flat_map(move |k| {
+        let l_min = (k + MIN_HAIRPIN + 1).max((j - 1).saturating_sub(MAX_LOOP - (k - i - 1)));
+        (l_min..j).map(move |l| (k, l))
+    })
+}
+
+/// Minimum free energy structure by Zuker's algorithm. Pairs spanning more
+/// than `max_span` bases are not formed when it is non-zero, which bounds
+/// the time and memory on long sequences.
+pub fn mfe(seq: &[u8], max_span: usize) -> Fold {
+    let n = seq.len();
+    let span = if max_span == 0 { n } else { max_span.min(n) };
+    let au = |i: usize, j: usize| dcal(terminal_penalty(seq[i], seq[j]));
+    let loops = LoopTable::new(seq, dcal);
+    let (closing, branch, base) = (dcal(ML_CLOSING + ML_BRANCH), dcal(ML_BRANCH), dcal(ML_BASE));
+
+    // `v[i][j]` is the best structure of `i..=j` with `i` paired to `j`,
+    // `wm[i][j]` the best part of a multiloop with at least one branch.
+    // `wm_by_end[j][i]` repeats `wm` so that splits read both halves in
+    // order.
+    let mut v = vec![INF; n * n];
+    let mut wm = vec![INF; n * n];
+    let mut wm_by_end = vec![INF; n * n];
+    let split = |a: &[i32], b: &[i32]| a.iter().zip(b).map(|(x, y)| x + y).min().unwrap_or(INF);
+    for d in MIN_HAIRPIN + 1..span {
+        for i in 0..n - d {
+            let j = i + d;
+            let mut best = INF;
+            if can_pair(seq[i], seq[j]) {
+                best = dcal(hairpin(seq, i, j));
+                for (k, l) in inner_pairs(i, j) {
+                    if v[k * n + l] < INF {
+                        best = best.min(loops.get(i, j, k, l) + v[k * n + l]);
+                    }
+                }
+                let multi = split(
+                    &wm[(i + 1) * n + i + 1..(i + 1) * n + j - 1],
+                    &wm_by_end[(j - 1) * n + i + 2..(j - 1) * n + j],
+                );
+                best = best.min(closing + au(i, j) + multi);
+            }
+            v[i * n + j] = best.min(INF);
+
+            let m = (best + branch + au(i, j))
+                .min(wm[(i + 1) * n + j] + base)
+                .min(wm[i * n + j - 1] + base)
+                .min(split(
+                    &wm[i * n + i + 1..i * n + j],
+                    &wm_by_end[j * n + i + 2..j * n + j + 1],
+                ))
+                .min(INF);
+            wm[i * n + j] = m;
+            wm_by_end[j * n + i] = m;
+        }
+    }
+
+    // `f[j]` is the best structure of the first `j` bases.
+    let mut f = vec![0; n + 1];
+    for j in 1..=n {
+        f[j] = f[j - 1];
+        for i in (j - 1).saturating_sub(span - 1)..j - 1 {
+            if v[i * n + j - 1] < INF {
+                f[j] = f[j].min(f[i] + v[i * n + j - 1] + au(i, j - 1));
+            }
+        }
+    }
+
+    enum Step {
+        Exterior(usize),
+        Pair(usize, usize),
+        Multi(usize, usize),
+    }
+    let mut pairs = Vec::new();
+    let mut todo = vec![Step::Exterior(n)];
+    while let Some(step) = todo.pop() {
+        match step {
+            Step::Exterior(0) => {}
+            Step::Exterior(j) => {
+                if f[j] == f[j - 1] {
+                    todo.push(Step::Exterior(j - 1));
+                    continue;
+                }
+                let i = (0..j - 1)
+                    .find(|&i| {
+                        v[i * n + j - 1] < INF && f[j] == f[i] + v[i * n + j - 1] + au(i, j - 1)
+                    })
+                    .expect("exterior loop traceback");
+                todo.push(Step::Pair(i, j - 1));
+                todo.push(Step::Exterior(i));
+            }
+            Step::Pair(i, j) => {
+                pairs.push((i, j));
+                let e = v[i * n + j];
+                if e == dcal(hairpin(seq, i, j)) {
+                    continue;
+                }
+                if let Some((k, l)) = inner_pairs(i, j)
+                    .find(|&(k, l)| v[k * n + l] < INF && e == loops.get(i, j, k, l) + v[k * n + l])
+                {
+                    todo.push(Step::Pair(k, l));
+                    continue;
+                }
+                let u = (i + 1..j - 1)
+                    .find(|&u| {
+                        e == closing + au(i, j) + wm[(i + 1) * n + u] + wm[(u + 1) * n + j - 1]
+                    })
+                    .expect("multiloop traceback");
+                todo.push(Step::Multi(i + 1, u));
+                todo.push(Step::Multi(u + 1, j - 1));
+            }
+            Step::Multi(i, j) => {
+                let e = wm[i * n + j];
+                if v[i * n + j] < INF && e == v[i * n + j] + branch + au(i, j) {
+                    todo.push(Step::Pair(i, j));
+                } else if e == wm[(i + 1) * n + j] + base {
+                    todo.push(Step::Multi(i + 1, j));
+                } else if e == wm[i * n + j - 1] + base {
+                    todo.push(Step::Multi(i, j - 1));
+                } else {
+                    let u = (i + 1..j)
+                        .find(|&u| e == wm[i * n + u] + wm[(u + 1) * n + j])
+                        .expect("multiloop traceback");
+                    todo.push(Step::Multi(i, u));
+                    todo.push(Step::Multi(u + 1, j));
+                }
+            }
+        }
+    }
+    Fold {
+        structure: dot_bracket(n, &pairs),
+        dg: f[n] as f64 / 100.0,
+    }
+}
+
+/// The Boltzmann ensemble of a sequence: its free energy in kcal/mol and
+/// the probability of every base pair above `1e-5`, as `(i, j, p)` with
+/// `i < j`, 0-based.
+pub struct Ensemble {
+    pub dg: f64,
+    pub pairs: Vec<(usize, usize, f64)>,
+}
+
+fn boltzmann(e: f64) -> f64 {
+    (-e / KT).exp()
+}
+
+/// Base-pair probabilities by McCaskill's algorithm, under the same model
+/// as [`mfe`]. The time is cubic and the memory quadratic in the length.
+/// Partition functions are scaled per base by the MFE `mfe_dg` to keep them
+/// within floating point range.
+pub fn partition(seq: &[u8], mfe_dg: f64) -> Ensemble {
+    let n = seq.len();
+    if n == 0 {
+        return Ensemble {
+            dg: 0.0,
+            pairs: Vec::new(),
+        };
+    }
+    let per_base = (1.07 * mfe_dg / (n as f64 * KT)).exp();
+    // `scale[k]` weighs `k` bases in any loop, `unpaired[k]` `k` unpaired
+    // bases in a multiloop.
+    let scale: Vec<f64> = (0..=n).map(|k| per_base.powi(k as i32)).collect();
+    let unpaired: Vec<f64> = (0..=n)
+        .map(|k| scale[k] * boltzmann(ML_BASE * k as f64))
+        .collect();
+    let loops = LoopTable::new(seq, boltzmann);
+    let branch = |i: usize, j: usize| boltzmann(ML_BRANCH + terminal_penalty(seq[i], seq[j]));
+    let closing =
+        |i: usize, j: usize| boltzmann(ML_CLOSING + ML_BRANCH + terminal_penalty(seq[i], seq[j]));
+
+    // `qb` with `i` paired to `j`, `qm1` a multiloop part whose only branch
+    // starts at `i`, `qm` one with at least one branch.
+    let mut qb = vec![0.0; n * n];
+    let mut qm1 = vec![0.0; n * n];
+    let mut qm = vec![0.0; n * n];
+    for d in MIN_HAIRPIN + 1..n {
+        for i in 0..n - d {
+            let j = i + d;
+            if can_pair(seq[i], seq[j]) {
+                let mut q = boltzmann(hairpin(seq, i, j)) * scale[d + 1];
+                for (k, l) in inner_pairs(i, j) {
+                    if qb[k * n + l] > 0.0 {
+                        q += loops.get(i, j, k, l) * qb[k * n + l] * scale[k - i + j - l];
+                    }
+                }
+                let mut multi = 0.0;
+                for u in i + 2..j {
+                    multi += qm[(i + 1) * n + u - 1] * qm1[u * n + j - 1];
+                }
+                qb[i * n + j] = q + multi * closing(i, j) * scale[2];
+            }
+
+            let mut q1 = 0.0;
+            for l in i + MIN_HAIRPIN + 1..=j {
+                q1 += qb[i * n + l] * branch(i, l) * unpaired[j - l];
+            }
+            qm1[i * n + j] = q1;
+            let mut q = q1;
+            for u in i + 1..=j {
+                q += (unpaired[u - i] + qm[i * n + u - 1]) * qm1[u * n + j];
+            }
+            qm[i * n + j] = q;
+        }
+    }
+
+    // Exterior loop over prefixes, `prefix[j]` for the first `j` bases, and
+    // suffixes, `suffix[i]` for the bases from `i` on.
+    let exterior = |i: usize, j: usize| qb[i * n + j] * boltzmann(terminal_penalty(seq[i], seq[j]));
+    let mut prefix = vec![1.0; n + 1];
+    for j in 1..=n {
+        prefix[j] = prefix[j - 1] * scale[1];
+        for i in 0..j - 1 {
+            prefix[j] += prefix[i] * exterior(i, j - 1);
+        }
+    }
+    let mut suffix = vec![1.0; n + 1];
+    for i in (0..n).rev() {
+        suffix[i] = suffix[i + 1] * scale[1];
+        for j in i + 1..n {
+            suffix[i] += exterior(i, j) * suffix[j + 1];
+        }
+    }
+    let z = prefix[n];
+
+    // Outside pass, from the longest pairs in. `right_any[o][j]` and
+    // `right_branch[o][j]` sum, over multiloops closed by `(o, q)` with
+    // `q > j`, the weight of the bases `j + 1..q` right of a branch ending
+    // at `j`: anything, or at least one more branch. Each is filled once
+    // every pair longer than `j - o` is known.
+    let mut prob = vec![0.0; n * n];
+    let mut closed = vec![0.0; n * n];
+    let mut right_any = vec![0.0; n * n];
+    let mut right_branch = vec![0.0; n * n];
+    for d in (MIN_HAIRPIN + 1..n).rev() {
+        for i in 0..n - d {
+            let j = i + d;
+            let b = qb[i * n + j];
+            if b == 0.0 {
+                continue;
+            }
+            let mut p = prefix[i] * exterior(i, j) * suffix[j + 1] / z;
+            for o in (i.saturating_sub(MAX_LOOP + 1)..i).rev() {
+                let left = i - o - 1;
+                for q in j + 1..n.min(j + 2 + MAX_LOOP - left) {
+                    if prob[o * n + q] > 0.0 {
+                        p += prob[o * n + q] / qb[o * n + q]
+                            * loops.get(o, q, i, j)
+                            * scale[i - o + q - j]
+                            * b;
+                    }
+                }
+            }
+            let mut multi = 0.0;
+            for o in 0..i {
+                multi += qm[(o + 1) * n + i - 1] * right_any[o * n + j]
+                    + unpaired[i - o - 1] * right_branch[o * n + j];
+            }
+            p += multi * b * branch(i, j);
+            prob[i * n + j] = p;
+            closed[i * n + j] = p / b * closing(i, j) * scale[2];
+        }
+
+        let jj = d - 1;
+        for o in 0..n - jj {
+            let j = o + jj;
+            let (mut any, mut both) = (0.0, 0.0);
+            for q in j + 1..n {
+                let w = closed[o * n + q];
+                if w > 0.0 {
+                    let inner = if q > j + 1 {
+                        qm[(j + 1) * n + q - 1]
+                    } else {
+                        0.0
+                    };
+                    any += w * (unpaired[q - j - 1] + inner);
+                    both += w * inner;
+                }
+            }
+            right_any[o * n + j] = any;
+            right_branch[o * n + j] = both;
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for i in 0..n {
+        for j in i + 1..n {
+            let p = prob[i * n + j];
+            if p > 1e-5 {
+                pairs.push((i, j, p.min(1.0)));
+            }
+        }
+    }
+    Ensemble {
+        dg: -KT * (z.ln() - n as f64 * per_base.ln()),
+        pairs,
+    }
+}
@@ -0,0 +1,231 @@
+use crate::energy::{can_pair, hairpin, is_wobble, terminal_penalty, two_loop};
+use crate::inverted::{IrParams, find_inverted_repeats};
+use std::collections::BTreeMap;
+
+pub struct HairpinParams {
+    /// Exact Watson-Crick stem a hairpin must contain to be considered.
+    pub seed: usize,
+    pub min_loop: usize,
+    pub max_loop: usize,
+    /// How far the stem may grow outwards from the loop, in bases per side.
+    pub max_extension: usize,
+    pub max_bulge: usize,
+    /// Hairpins less stable than this are dropped.
+    pub max_dg: f64,
+}
+
+/// A rho-independent terminator: a stable GC-rich hairpin followed by a
+/// U-tract on the transcript.
+pub struct TerminatorParams {
+    pub max_dg: f64,
+    pub min_stem_gc: f64,
+    pub tail: usize,
+    pub min_u: usize,
+}
+
+/// A predicted hairpin. `start` and `end` are on the forward strand,
+/// 0-based and end exclusive; `sequence`, `structure` and `tail` read 5' to
+/// 3' on the transcript, i.e. reverse complemented for `-`.
+#[derive(Debug)]
+pub struct Hairpin {
+    pub start: usize,
+    pub end: usize,
+    pub strand: char,
+    pub pairs: usize,
+    pub wobble_pairs: usize,
+    pub bulges: usize,
+    pub loop_len: usize,
+    pub dg: f64,
+    pub stem_gc: f64,
+    pub sequence: String,
+    pub structure: String,
+    pub tail: String,
+    pub u_tract: usize,
+    pub terminator: bool,
+}
+
+fn reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|&b| match b {
+            b'A' => b'T',
+            b'T' => b'A',
+            b'C' => b'G',
+            b'G' => b'C',
+            other => other,
+        })
+        .collect()
+}
+
+/// Best stem grown outwards from the pair `(i, j)` closing a hairpin loop,
+/// by dynamic programming over stacked pairs and bulges of up to
+/// `max_bulge` bases. Returns the free energy and the pairs, outermost
+/// first.
+fn grow_stem(s: &[u8], i: usize, j: usize, params: &HairpinParams) -> (f64, Vec<(usize, usize)>) {
+    let w = params.max_extension;
+    let size = w + 1;
+    let mut energy = vec![f64::INFINITY; size * size];
+    let mut from = vec![usize::MAX; size * size];
+    energy[0] = hairpin(s, i, j);
+
+    // State `(da, db)` is the pair `(i - da, j + db)`; every step outwards
+    // raises `da`, so rows can be filled in order.
+    let mut best = (energy[0] + terminal_penalty(s[i], s[j]), 0);
+    for da in 0..size {
+        for db in 0..size {
+            let e = energy[da * size + db];
+            if e.is_infinite() {
+                continue;
+            }
+            let (p, q) = (i - da, j + db);
+            let total = e + terminal_penalty(s[p], s[q]);
+            if total < best.0 {
+                best = (total, da * size + db);
+            }
+            let steps = (0..=params.max_bulge)
+                .map(|x| (x, 0))
+                .chain((1..=params.max_bulge).map(|y| (0, y)));
+            for (x, y) in steps {
+                let (na, nb) = (da + 1 + x, db + 1 + y);
+                if na >= size || nb >= size || na > i || j + nb >= s.len() {
+                    continue;
+                }
+                let (a, b) = (i - na, j + nb);
+                if !can_pair(s[a], s[b]) {
+                    continue;
+                }
+                let step = two_loop(s, a, b, p, q);
+                let k = na * size + nb;
+                if e + step < energy[k] {
+                    energy[k] = e + step;
+                    from[k] = da * size + db;
+                }
+            }
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut k = best.1;
+    loop {
+        pairs.push((i - k / size, j + k % size));
+        if k == 0 {
+            break;
+        }
+        k = from[k];
+    }
+    (best.0, pairs)
+}
+
+/// Hairpins of one strand, in that strand's coordinates.
+fn strand_hairpins(s: &[u8], params: &HairpinParams) -> Vec<Hairpin> {
+    let text = String::from_utf8_lossy(s);
+    // Longer exact stems come back as their pairs next to the loop, which is
+    // where `grow_stem` starts from.
+    let seeds = find_inverted_repeats(
+        &text,
+        &IrParams {
+            min_stem: params.seed,
+            max_stem: params.max_extension + 1,
+            min_spacer: params.min_loop,
+            max_spacer: params.max_loop,
+            max_mismatches: 0,
+        },
+    );
+
+    let mut found = Vec::new();
+    for ir in seeds {
+        // The seed is an exact Watson-Crick stem; pairs inside it, G-U
+        // included, may close a smaller loop more stably.
+        let (mut i, mut j) = (ir.left + ir.len - 1, ir.right);
+        let (mut dg, mut pairs) = grow_stem(s, i, j, params);
+        let mut loop_len = j - i - 1;
+        while j - i - 3 >= params.min_loop && can_pair(s[i + 1], s[j - 1]) {
+            (i, j) = (i + 1, j - 1);
+            let (inner_dg, inner_pairs) = grow_stem(s, i, j, params);
+            if inner_dg < dg {
+                (dg, pairs, loop_len) = (inner_dg, inner_pairs, j - i - 1);
+            }
+        }
+        if dg > params.max_dg {
+            continue;
+        }
+        let (start, end) = (pairs[0].0, pairs[0].1 + 1);
+        let mut structure = vec![b'.'; end - start];
+        for &(a, b) in &pairs {
+            structure[a - start] = b'(';
+            structure[b - start] = b')';
+        }
+        let bulges = pairs
+            .windows(2)
+            .filter(|w| w[1].0 - w[0].0 > 1 || w[0].1 - w[1].1 > 1)
+            .count();
+        let gc = pairs
+            .iter()
+            .filter(|&&(a, b)| matches!((s[a], s[b]), (b'G', b'C') | (b'C', b'G')))
+            .count();
+        found.push(Hairpin {
+            start,
+            end,
+            strand: '+',
+            pairs: pairs.len(),
+            wobble_pairs: pairs
+                .iter()
+                .filter(|&&(a, b)| is_wobble(s[a], s[b]))
+                .count(),
+            bulges,
+            loop_len,
+            dg: (dg * 100.0).round() / 100.0,
+            stem_gc: gc as f64 / pairs.len() as f64,
+            sequence: String::from_utf8_lossy(&s[start..end]).into_owned(),
+            structure: String::from_utf8_lossy(&structure).into_owned(),
+            tail: String::new(),
+            u_tract: 0,
+            terminator: false,
+        });
+    }
+    found
+}
+
+/// Hairpins on both strands, keeping the most stable of those overlapping on
+/// the same strand, with terminators flagged by their stem and the U-tract
+/// that follows.
+pub fn find_hairpins(seq: &str, params: &HairpinParams, term: &TerminatorParams) -> Vec<Hairpin> {
+    let forward = seq.to_ascii_uppercase().into_bytes();
+    let reverse = reverse_complement(&forward);
+    let n = forward.len();
+
+    let mut all = Vec::new();
+    for (strand, s) in [('+', &forward), ('-', &reverse)] {
+        for mut h in strand_hairpins(s, params) {
+            let tail = &s[h.end..(h.end + term.tail).min(n)];
+            h.tail = String::from_utf8_lossy(tail).into_owned();
+            h.u_tract = tail.iter().filter(|&&b| b == b'T').count();
+            h.terminator =
+                h.dg <= term.max_dg && h.stem_gc >= term.min_stem_gc && h.u_tract >= term.min_u;
+            if strand == '-' {
+                (h.start, h.end) = (n - h.end, n - h.start);
+                h.strand = '-';
+            }
+            all.push(h);
+        }
+    }
+
+    all.sort_by(|a, b| a.dg.total_cmp(&b.dg).then(a.start.cmp(&b.start)));
+    // Kept spans per strand, by start; they never overlap, so only the
+    // span starting last before a candidate's end can collide with it.
+    let mut taken: [BTreeMap<usize, usize>; 2] = [BTreeMap::new(), BTreeMap::new()];
+    let mut kept: Vec<Hairpin> = Vec::new();
+    for h in all {
+        let spans = &mut taken[(h.strand == '-') as usize];
+        let clash = spans
+            .range(..h.end)
+            .next_back()
+            .is_some_and(|(_, &end)| end > h.start);
+        if !clash {
+            spans.insert(h.start, h.end);
+            kept.push(h);
+        }
+    }
+    kept.sort_by_key(|h| (h.start, h.strand));
+    kept
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+/// What the right arm is of the left one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    /// The reverse complement: an inverted repeat, or a palindrome when
+    /// the arms touch.
+    Inverted,
+    /// The reverse: a mirror repeat, which can fold into H-DNA when the
+    /// arms are all purines or all pyrimidines.
+    Mirror,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    InvertedRepeat,
+    Palindrome,
+    MirrorRepeat,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::InvertedRepeat => "inverted_repeat",
+            Kind::Palindrome => "palindrome",
+            Kind::MirrorRepeat => "mirror_repeat",
+        }
+    }
+}
+
+/// Two arms of `len` bases at `left` and `right` (0-based), the right one
+/// the reverse complement (or, for a mirror repeat, the reverse) of the
+/// left but for `mismatches` positions.
+#[derive(Debug)]
+pub struct InvertedRepeat {
+    pub kind: Kind,
+    pub left: usize,
+    pub right: usize,
+    pub len: usize,
+    pub mismatches: usize,
+    pub arm: String,
+    pub partner: String,
+}
+
+impl InvertedRepeat {
+    pub fn spacer(&self) -> usize {
+        self.right - (self.left + self.len)
+    }
+
+    /// Share of the left arm in the larger of its purines and pyrimidines;
+    /// 1.0 marks a homopurine or homopyrimidine arm.
+    pub fn purine_bias(&self) -> f64 {
+        let purines = self
+            .arm
+            .bytes()
+            .filter(|b| matches!(b, b'A' | b'G'))
+            .count();
+        purines.max(self.len - purines) as f64 / self.len as f64
+    }
+}
+
+/// Stem and spacer (loop) lengths to search for, both inclusive, and the
+/// mismatches allowed between the arms. A stem longer than `max_stem` is
+/// reported as its `max_stem` innermost pairs, those next to the spacer.
+pub struct IrParams {
+    pub min_stem: usize,
+    pub max_stem: usize,
+    pub min_spacer: usize,
+    pub max_spacer: usize,
+    pub max_mismatches: usize,
+}
+
+/// Longest seed that fits a `u64` at two bits per base.
+const MAX_SEED: usize = 32;
+
+fn code(b: u8) -> Option<u64> {
+    match b {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    }
+}
+
+fn matches(a: u8, b: u8, symmetry: Symmetry) -> bool {
+    match symmetry {
+        Symmetry::Inverted => matches!(
+            (a, b),
+            (b'A', b'T') | (b'T', b'A') | (b'C', b'G') | (b'G', b'C')
+        ),
+        Symmetry::Mirror => a == b && code(a).is_some(),
+    }
+}
+
+/// How many matching positions to take along one direction of a stem: the
+/// run up to the `budget + 1`-th mismatch, trimmed back to its last match,
+/// as `(length, mismatches)` for every budget up to `max`.
+fn runs(mut ok: impl FnMut(usize) -> Option<bool>, max: usize) -> Vec<(usize, usize)> {
+    let mut out = vec![(0, 0); max + 1];
+    let (mut mm, mut t) = (0, 0);
+    let mut last = (0, 0);
+    while let Some(hit) = ok(t) {
+        if hit {
+            last = (t + 1, mm);
+        } else {
+            out[mm] = last;
+            mm += 1;
+            if mm > max {
+                break;
+            }
+        }
+        t += 1;
+    }
+    for slot in out.iter_mut().skip(mm) {
+        *slot = last;
+    }
+    out
+}
+
+/// Packed `k`-mers and their reverse complements (or reverses) at every
+/// position where the window holds only ACGT.
+fn kmers(seq: &[u8], k: usize, symmetry: Symmetry) -> Vec<Option<(u64, u64)>> {
+    let mask = if k == MAX_SEED {
+        u64::MAX
+    } else {
+        (1 << (2 * k)) - 1
+    };
+    let mut out = vec![None; seq.len().saturating_sub(k - 1)];
+    let (mut fwd, mut rev, mut valid) = (0u64, 0u64, 0usize);
+    for (i, &b) in seq.iter().enumerate() {
+        match code(b) {
+            Some(c) => {
+                fwd = ((fwd << 2) | c) & mask;
+                let partner = match symmetry {
+                    Symmetry::Inverted => 3 - c,
+                    Symmetry::Mirror => c,
+                };
+                rev = (rev >> 2) | (partner << (2 * (k - 1)));
+                valid += 1;
+            }
+            None => valid = 0,
+        }
+        if valid >= k {
+            out[i + 1 - k] = Some((fwd, rev));
+        }
+    }
+    out
+}
+
+/// Every maximal inverted repeat whose stem and spacer fall in the given
+/// ranges; see [`find_repeats`].
+pub fn find_inverted_repeats(seq: &str, params: &IrParams) -> Vec<InvertedRepeat> {
+    find_repeats(seq, params, Symmetry::Inverted)
+}
+
+/// Every maximal repeat of the given symmetry whose stem, spacer and
+/// mismatches fall in the given ranges. Stems are seeded by exact k-mers
+/// found through a hash of their reverse complements (or reverses); with
+/// `m` mismatches one of `m + 1` stretches of a stem is exact, so the seed
+/// is `(min_stem - m) / (m + 1)` bases long (at most 32). Each seed is
+/// extended outwards and inwards, the inner end no closer than
+/// `min_spacer`, spending the mismatch budget where it buys the longest
+/// stem, and no further than `max_stem`; a longer stem is cut back to its
+/// innermost `max_stem` pairs. Of the stems found around one centre,
+/// overlapping ones give way to the longest, then to the innermost. The work grows with the sequence length plus the number
+/// of seed pairs, not with the product of length and spacer.
+pub fn find_repeats(seq: &str, params: &IrParams, symmetry: Symmetry) -> Vec<InvertedRepeat> {
+    let s = seq.as_bytes();
+    let n = s.len();
+    let m = params.max_mismatches;
+    let k = (params.min_stem.saturating_sub(m) / (m + 1)).clamp(1, MAX_SEED);
+    if params.min_stem > params.max_stem || n < 2 * params.min_stem + params.min_spacer {
+        return Vec::new();
+    }
+
+    let windows = kmers(s, k, symmetry);
+    let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (j, w) in windows.iter().enumerate() {
+        if let Some((fwd, _)) = w {
+            index.entry(*fwd).or_default().push(j);
+        }
+    }
+
+    // A seed nearer the outer end has a spacer longer than the stem's own
+    // by twice the part of the stem inside it.
+    let seed_spacer_max = params.max_spacer + 2 * params.max_stem.saturating_sub(k);
+    // Candidates keyed by their centre, `left + right + len`.
+    let mut by_centre: HashMap<usize, Vec<(usize, usize, usize)>> = HashMap::new();
+    for (i, w) in windows.iter().enumerate() {
+        let Some((_, partner)) = w else {
+            continue;
+        };
+        let Some(rights) = index.get(partner) else {
+            continue;
+        };
+        let lo = i + k + params.min_spacer;
+        let hi = i + k + seed_spacer_max;
+        let from = rights.partition_point(|&j| j < lo);
+        for &j in rights[from..].iter().take_while(|&&j| j <= hi) {
+            let outer = j + k;
+            // An exact stem is found once, from its innermost seed, so that
+            // growing outwards keeps the pairs next to the spacer.
+            if m == 0
+                && outer >= i + 2 * (k + 1) + params.min_spacer
+                && matches(s[i + k], s[j - 1], symmetry)
+            {
+                continue;
+            }
+            let cap = params.max_stem - k.min(params.max_stem);
+            let outward = runs(
+                |t| {
+                    (t < cap && t < i && outer + t < n)
+                        .then(|| matches(s[i - 1 - t], s[outer + t], symmetry))
+                },
+                m,
+            );
+            let inward = runs(
+                |t| {
+                    // The spacer left once this pair is in the stem.
+                    let room = outer >= i + 2 * (k + t + 1) + params.min_spacer;
+                    (t < cap && room).then(|| matches(s[i + k + t], s[outer - 1 - k - t], symmetry))
+                },
+                m,
+            );
+            let Some((out_len, in_len, mm)) = (0..=m)
+                .map(|a| {
+                    (
+                        outward[a].0,
+                        inward[m - a].0,
+                        outward[a].1 + inward[m - a].1,
+                    )
+                })
+                .max_by_key(|&(o, i, mm)| (o + i, std::cmp::Reverse(mm)))
+            else {
+                continue;
+            };
+
+            let mut len = k + out_len + in_len;
+            let mut left = i - out_len;
+            let right = outer + out_len - len;
+            let mut mm = mm;
+            if len > params.max_stem {
+                // Cut from the outer end, which leaves the spacer as it is,
+                // and on to the next matching pair.
+                left += len - params.max_stem;
+                len = params.max_stem;
+                while len > 0 && !matches(s[left], s[right + len - 1], symmetry) {
+                    left += 1;
+                    len -= 1;
+                }
+                mm = (0..len)
+                    .filter(|&t| !matches(s[left + t], s[right + len - 1 - t], symmetry))
+                    .count();
+            }
+            let spacer = right - (left + len);
+            if len < params.min_stem || spacer < params.min_spacer || spacer > params.max_spacer {
+                continue;
+            }
+            by_centre
+                .entry(left + right + len)
+                .or_default()
+                .push((len, mm, left));
+        }
+    }
+
+    let mut found = Vec::new();
+    for (centre, mut candidates) in by_centre {
+        candidates.sort_unstable_by_key(|&(len, mm, left)| {
+            (std::cmp::Reverse(len), mm, std::cmp::Reverse(left))
+        });
+        candidates.dedup();
+        let mut kept: Vec<(usize, usize)> = Vec::new();
+        for (len, mm, left) in candidates {
+            if kept.iter().any(|&(l, e)| left < e && l < left + len) {
+                continue;
+            }
+            kept.push((left, left + len));
+            let right = centre - left - len;
+            let kind = match symmetry {
+                Symmetry::Mirror => Kind::MirrorRepeat,
+                Symmetry::Inverted if right == left + len => Kind::Palindrome,
+                Symmetry::Inverted => Kind::InvertedRepeat,
+            };
+            found.push(InvertedRepeat {
+                kind,
+                left,
+                right,
+                len,
+                mismatches: mm,
+                arm: String::from_utf8_lossy(&s[left..left + len]).into_owned(),
+                partner: String::from_utf8_lossy(&s[right..right + len]).into_owned(),
+            });
+        }
+    }
+    found.sort_by_key(|r| (r.left, r.right));
+    found
+}
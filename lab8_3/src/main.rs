@@ -1,87 +1,515 @@
+mod annotation;
+mod energy;
+mod fold;
+mod hairpin;
+mod inverted;
+
+use annotation::{Feature, write_bed, write_gff3, write_soft_masked};
+use hairpin::{Hairpin, HairpinParams, TerminatorParams, find_hairpins};
+use inverted::{InvertedRepeat, IrParams, Kind, Symmetry, find_repeats};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
+use std::io::{self, Write};
 
-fn complement(base: char) -> char {
-    match base {
-        'A' => 'T',
-        'T' => 'A',
-        'C' => 'G',
-        'G' => 'C',
-        _ => 'N',
+/// Records as `(id, sequence)`, the id being the first word of the header.
+fn read_fasta(path: &str) -> Vec<(String, String)> {
+    let content = fs::read_to_string(path).expect("Cannot read FASTA file");
+    let mut records: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        if let Some(header) = line.strip_prefix('>') {
+            let id = header.split_whitespace().next().unwrap_or("").to_string();
+            records.push((id, String::new()));
+        } else {
+            if records.is_empty() {
+                records.push(("seq".to_string(), String::new()));
+            }
+            if let Some((_, seq)) = records.last_mut() {
+                seq.push_str(line.trim());
+            }
+        }
     }
+    records
 }
 
-fn reverse_complement(seq: &str) -> String {
-    seq.chars().rev().map(complement).collect()
+fn arg_value(args: &[String], name: &str) -> Option<String> {
+    let eq = format!("{}=", name);
+    let mut it = args.iter().skip(1);
+    while let Some(tok) = it.next() {
+        if tok == name {
+            return it.next().cloned();
+        } else if let Some(v) = tok.strip_prefix(&eq) {
+            return Some(v.to_string());
+        }
+    }
+    None
 }
 
-fn read_fasta(path: &str) -> String {
-    let content = fs::read_to_string(path).expect("Cannot read FASTA file");
-    content
-        .lines()
-        .filter(|line| !line.starts_with('>'))
-        .collect::<String>()
-}
+/// Flags that take no value.
+const BARE_FLAGS: [&str; 2] = ["--mirror", "--nussinov"];
 
-fn find_inverted_repeats(seq: &str, min_len: usize, max_len: usize) {
-    let chars: Vec<char> = seq.chars().collect();
-    let n = chars.len();
+fn fasta_path(args: &[String]) -> Option<String> {
+    let mut it = args.iter().skip(1);
+    while let Some(tok) = it.next() {
+        if tok.starts_with("--") {
+            if !tok.contains('=') && !BARE_FLAGS.contains(&tok.as_str()) {
+                it.next();
+            }
+        } else {
+            return Some(tok.clone());
+        }
+    }
+    None
+}
 
-    let max_spacer = 200;
-    let mut count4 = 0;
-    let mut count5 = 0;
-    let mut count6 = 0;
+/// GFF3/BED features: one per repeat, spanning both arms, with the arms as
+/// the parts so that only they are masked.
+fn repeat_features(hits: &[(String, InvertedRepeat)]) -> Vec<Feature> {
+    hits.iter()
+        .map(|(seqid, ir)| {
+            let spacer = ir.spacer();
+            Feature {
+                seqid: seqid.clone(),
+                start: ir.left,
+                end: ir.right + ir.len,
+                kind: ir.kind.name(),
+                name: match ir.kind {
+                    Kind::MirrorRepeat => format!("MR{}_sp{}", ir.len, spacer),
+                    _ => format!("IR{}_sp{}", ir.len, spacer),
+                },
+                score: ir.len as f64,
+                strand: '.',
+                parts: vec![(ir.left, ir.left + ir.len), (ir.right, ir.right + ir.len)],
+                attributes: vec![
+                    ("motif", ir.arm.clone()),
+                    ("arm_length", ir.len.to_string()),
+                    ("spacer", spacer.to_string()),
+                    ("mismatches", ir.mismatches.to_string()),
+                    ("copies", "2".to_string()),
+                ],
+            }
+        })
+        .collect()
+}
 
-    for len in min_len..=max_len {
-        println!("Searching IR of length {}", len);
+fn arg_number<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> io::Result<T> {
+    match arg_value(args, name) {
+        Some(v) => v.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} expects a number", name),
+            )
+        }),
+        None => Ok(default),
+    }
+}
 
-        for i in 0..=n - len {
-            let left: String = chars[i..i + len].iter().collect();
-            let rc = reverse_complement(&left);
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).is_some_and(|a| a == "fold") {
+        return run_fold(&args[1..]);
+    }
 
-            let end_j = (i + len + max_spacer).min(n - len);
+    let Some(fasta_path) = fasta_path(&args) else {
+        println!(
+            "Usage. cargo run <path_to_fasta> [--min-stem 4] [--max-stem 6] [--min-spacer 0] \
+             [--max-spacer 200] [--mismatches 0] [--mirror] [--show 20] [--tsv out.tsv] \
+             [--summary summary.tsv] [--json out.json] [--bed out.bed] [--gff out.gff3] \
+             [--masked out.fasta] [--hairpins hairpins.gff3]\n       \
+             cargo run fold <path_to_fasta> [--nussinov] [--max-span 0] [--pf-max-length 500] \
+             [--out folds.txt] [--bpp pairs.tsv]"
+        );
+        return Ok(());
+    };
+    let params = IrParams {
+        min_stem: arg_number(&args, "--min-stem", 4)?,
+        max_stem: arg_number(&args, "--max-stem", 6)?,
+        min_spacer: arg_number(&args, "--min-spacer", 0)?,
+        max_spacer: arg_number(&args, "--max-spacer", 200)?,
+        max_mismatches: arg_number(&args, "--mismatches", 0)?,
+    };
+    if params.min_stem == 0 || params.min_stem > params.max_stem {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "stem lengths must satisfy 0 < --min-stem <= --max-stem",
+        ));
+    }
+    if params.max_mismatches >= params.min_stem {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--mismatches must be smaller than --min-stem",
+        ));
+    }
+    let mut symmetries = vec![Symmetry::Inverted];
+    if args.iter().any(|a| a == "--mirror") {
+        symmetries.push(Symmetry::Mirror);
+    }
+    let show = arg_number(&args, "--show", 20)?;
+    let records = read_fasta(&fasta_path);
 
-            for j in i + len..=end_j {
-                let right: String = chars[j..j + len].iter().collect();
+    let mut hits = Vec::new();
+    for (id, seq) in &records {
+        let seq_upper = seq.to_uppercase();
 
-                if right == rc {
-                    println!(
-                        "IR {} bp. {} at {} <-> {} at {}",
-                        len, left, i, right, j
-                    );
+        println!("Loaded {} with {} bases", id, seq_upper.len());
+        println!(
+            "Searching for repeats with stems of {} to {} bp, spacers of {} to {} bp and up to {} mismatches",
+            params.min_stem,
+            params.max_stem,
+            params.min_spacer,
+            params.max_spacer,
+            params.max_mismatches
+        );
 
-                    match len {
-                        4 => count4 += 1,
-                        5 => count5 += 1,
-                        6 => count6 += 1,
-                        _ => (),
-                    }
-                }
+        for &symmetry in &symmetries {
+            let found = find_repeats(&seq_upper, &params, symmetry);
+            for ir in found.iter().take(show) {
+                println!(
+                    "{} {} bp, {} mismatches. {} at {} <-> {} at {}",
+                    ir.kind.name(),
+                    ir.len,
+                    ir.mismatches,
+                    ir.arm,
+                    ir.left,
+                    ir.partner,
+                    ir.right
+                );
             }
+            if found.len() > show {
+                println!("... and {} more", found.len() - show);
+            }
+            hits.extend(found.into_iter().map(|ir| (id.clone(), ir)));
         }
     }
 
+    let summary = summarize(&hits);
     println!();
     println!("Summary:");
-    println!("IR of length 4: {}", count4);
-    println!("IR of length 5: {}", count5);
-    println!("IR of length 6: {}", count6);
+    for ((kind, len), count) in &summary {
+        println!("{} of length {}: {}", kind, len, count);
+    }
+    if let Some(out) = arg_value(&args, "--tsv") {
+        write_tsv(&out, &hits)?;
+        println!("TSV written to {}", out);
+    }
+    if let Some(out) = arg_value(&args, "--summary") {
+        write_summary_tsv(&out, &summary)?;
+        println!("Summary TSV written to {}", out);
+    }
+    if let Some(out) = arg_value(&args, "--json") {
+        write_json(&out, &hits, &summary)?;
+        println!("JSON written to {}", out);
+    }
+
+    let features = repeat_features(&hits);
+    if let Some(out) = arg_value(&args, "--bed") {
+        write_bed(&out, &features)?;
+        println!("BED written to {}", out);
+    }
+    if let Some(out) = arg_value(&args, "--gff") {
+        write_gff3(&out, "lab8_3", &features)?;
+        println!("GFF3 written to {}", out);
+    }
+    if let Some(out) = arg_value(&args, "--masked") {
+        write_soft_masked(&out, &records, &features)?;
+        println!("Soft-masked FASTA written to {}", out);
+    }
+    if let Some(out) = arg_value(&args, "--hairpins") {
+        predict_hairpins(&args, &records, &out)?;
+    }
+    Ok(())
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// Repeats per kind and arm length.
+fn summarize(hits: &[(String, InvertedRepeat)]) -> BTreeMap<(&'static str, usize), usize> {
+    let mut counts = BTreeMap::new();
+    for (_, ir) in hits {
+        *counts.entry((ir.kind.name(), ir.len)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// H-DNA needs a mirror repeat with arms of (nearly) only purines or only
+/// pyrimidines.
+fn h_dna_candidate(ir: &InvertedRepeat) -> bool {
+    ir.kind == Kind::MirrorRepeat && ir.purine_bias() >= 0.9
+}
 
-    if args.len() != 2 {
-        println!("Usage. cargo run <path_to_fasta>");
-        return;
+/// One line per repeat; positions are 1-based, as in GFF3.
+fn write_tsv(path: &str, hits: &[(String, InvertedRepeat)]) -> io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    writeln!(
+        out,
+        "record\tkind\tleft_start\tright_start\tarm_length\tspacer\tmismatches\tleft_arm\tright_arm\tpurine_bias\th_dna"
+    )?;
+    for (id, ir) in hits {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.2}\t{}",
+            id,
+            ir.kind.name(),
+            ir.left + 1,
+            ir.right + 1,
+            ir.len,
+            ir.spacer(),
+            ir.mismatches,
+            ir.arm,
+            ir.partner,
+            ir.purine_bias(),
+            h_dna_candidate(ir)
+        )?;
+    }
+    Ok(())
+}
+
+fn write_summary_tsv(path: &str, summary: &BTreeMap<(&str, usize), usize>) -> io::Result<()> {
+    let mut out = fs::File::create(path)?;
+    writeln!(out, "kind\tarm_length\tcount")?;
+    for ((kind, len), count) in summary {
+        writeln!(out, "{}\t{}\t{}", kind, len, count)?;
+    }
+    Ok(())
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `{"summary": {kind: {length: count}}, "repeats": [...]}`, positions
+/// 1-based as in the TSV.
+fn write_json(
+    path: &str,
+    hits: &[(String, InvertedRepeat)],
+    summary: &BTreeMap<(&str, usize), usize>,
+) -> io::Result<()> {
+    let mut by_kind: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for ((kind, len), count) in summary {
+        by_kind
+            .entry(kind)
+            .or_default()
+            .push(format!("\"{}\": {}", len, count));
+    }
+    let kinds: Vec<String> = by_kind
+        .iter()
+        .map(|(kind, lengths)| format!("    {}: {{{}}}", json_string(kind), lengths.join(", ")))
+        .collect();
+
+    let mut out = fs::File::create(path)?;
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"summary\": {{\n{}\n  }},", kinds.join(",\n"))?;
+    writeln!(out, "  \"repeats\": [")?;
+    for (n, (id, ir)) in hits.iter().enumerate() {
+        writeln!(
+            out,
+            "    {{\"record\": {}, \"kind\": \"{}\", \"left_start\": {}, \"right_start\": {}, \"arm_length\": {}, \"spacer\": {}, \"mismatches\": {}, \"left_arm\": \"{}\", \"right_arm\": \"{}\", \"purine_bias\": {:.2}, \"h_dna\": {}}}{}",
+            json_string(id),
+            ir.kind.name(),
+            ir.left + 1,
+            ir.right + 1,
+            ir.len,
+            ir.spacer(),
+            ir.mismatches,
+            ir.arm,
+            ir.partner,
+            ir.purine_bias(),
+            h_dna_candidate(ir),
+            if n + 1 < hits.len() { "," } else { "" }
+        )?;
+    }
+    writeln!(out, "  ]")?;
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+/// GFF3 track of the hairpins: `terminator` for those that qualify, the
+/// others `stem_loop`.
+fn hairpin_features(hits: &[(String, Hairpin)]) -> Vec<Feature> {
+    hits.iter()
+        .map(|(seqid, h)| {
+            let mut attributes = vec![
+                ("dG", format!("{:.2}", h.dg)),
+                ("stem_pairs", h.pairs.to_string()),
+                ("gu_pairs", h.wobble_pairs.to_string()),
+                ("bulges", h.bulges.to_string()),
+                ("loop", h.loop_len.to_string()),
+                ("stem_gc", format!("{:.2}", h.stem_gc)),
+                ("sequence", h.sequence.clone()),
+                ("structure", h.structure.clone()),
+            ];
+            if h.terminator {
+                attributes.push(("u_tract", h.tail.clone()));
+            }
+            Feature {
+                seqid: seqid.clone(),
+                start: h.start,
+                end: h.end,
+                kind: if h.terminator {
+                    "terminator"
+                } else {
+                    "stem_loop"
+                },
+                name: format!("{}{:.1}", if h.terminator { "T" } else { "SL" }, h.dg),
+                score: h.dg,
+                strand: h.strand,
+                parts: vec![(h.start, h.end)],
+                attributes,
+            }
+        })
+        .collect()
+}
+
+/// `--hairpins out.gff3`: stem-loops grown from the inverted repeats, with
+/// nearest-neighbour free energies, and rho-independent terminators.
+fn predict_hairpins(args: &[String], records: &[(String, String)], out: &str) -> io::Result<()> {
+    let params = HairpinParams {
+        seed: arg_number(args, "--hairpin-seed", 4)?,
+        min_loop: arg_number(args, "--min-loop", 3)?,
+        max_loop: arg_number(args, "--max-loop", 12)?,
+        max_extension: arg_number(args, "--max-extension", 30)?,
+        max_bulge: arg_number(args, "--max-bulge", 2)?,
+        max_dg: arg_number(args, "--max-dg", -5.0)?,
+    };
+    let term = TerminatorParams {
+        max_dg: arg_number(args, "--terminator-dg", -8.0)?,
+        min_stem_gc: arg_number(args, "--terminator-gc", 0.5)?,
+        tail: arg_number(args, "--tail", 8)?,
+        min_u: arg_number(args, "--min-u", 4)?,
+    };
+    if params.seed == 0 || params.min_loop < 3 || params.min_loop > params.max_loop {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "hairpins need a seed of at least 1 bp and loops of at least 3 bases",
+        ));
     }
 
-    let fasta_path = &args[1];
-    let seq = read_fasta(fasta_path);
-    let seq_upper = seq.to_uppercase();
+    let mut hits = Vec::new();
+    for (id, seq) in records {
+        hits.extend(
+            find_hairpins(seq, &params, &term)
+                .into_iter()
+                .map(|h| (id.clone(), h)),
+        );
+    }
+    let terminators = hits.iter().filter(|(_, h)| h.terminator).count();
+    println!();
+    println!(
+        "{} hairpins with dG <= {} kcal/mol, {} rho-independent terminators",
+        hits.len(),
+        params.max_dg,
+        terminators
+    );
+    let mut strongest: Vec<&(String, Hairpin)> =
+        hits.iter().filter(|(_, h)| h.terminator).collect();
+    strongest.sort_by(|a, b| a.1.dg.total_cmp(&b.1.dg));
+    for (id, h) in strongest.iter().take(10) {
+        println!(
+            "{} {}..{} ({}) dG {:.2} {} {} {}",
+            id,
+            h.start + 1,
+            h.end,
+            h.strand,
+            h.dg,
+            h.sequence,
+            h.structure,
+            h.tail
+        );
+    }
+    write_gff3(out, "lab8_3", &hairpin_features(&hits))?;
+    println!("Hairpin GFF3 written to {}", out);
+    Ok(())
+}
 
-    println!("Loaded sequence with {} bases", seq_upper.len());
-    println!("Searching for inverted repeats of length 4 to 6");
+/// `fold`: the MFE structure of every record with its free energy, the
+/// Nussinov structure with `--nussinov`, and for records no longer than
+/// `--pf-max-length` the ensemble free energy and base-pair probabilities.
+fn run_fold(args: &[String]) -> io::Result<()> {
+    let Some(path) = fasta_path(args) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "fold needs a FASTA file",
+        ));
+    };
+    let max_span = arg_number(args, "--max-span", 0)?;
+    let pf_max_length = arg_number(args, "--pf-max-length", 500)?;
+    let nussinov = args.iter().any(|a| a == "--nussinov");
+
+    let mut out = match arg_value(args, "--out") {
+        Some(p) => Some((fs::File::create(&p)?, p)),
+        None => None,
+    };
+    let mut bpp = match arg_value(args, "--bpp") {
+        Some(p) => {
+            let mut file = fs::File::create(&p)?;
+            writeln!(file, "id\ti\tj\tprobability")?;
+            Some((file, p))
+        }
+        None => None,
+    };
+
+    for (id, seq) in read_fasta(&path) {
+        let rna: Vec<u8> = seq
+            .bytes()
+            .map(|b| match b.to_ascii_uppercase() {
+                b'T' => b'U',
+                other => other,
+            })
+            .collect();
+        let text = String::from_utf8_lossy(&rna);
+        println!(">{} ({} nt)", id, rna.len());
+        println!("{}", text);
+        if nussinov {
+            let (structure, pairs) = fold::nussinov(&rna);
+            println!("{} ({} pairs, Nussinov)", structure, pairs);
+        }
+        let mfe = fold::mfe(&rna, max_span);
+        println!("{} ({:.2})", mfe.structure, mfe.dg);
+        if let Some((file, _)) = out.as_mut() {
+            writeln!(file, ">{}\n{}\n{} ({:.2})", id, text, mfe.structure, mfe.dg)?;
+        }
 
-    find_inverted_repeats(&seq_upper, 4, 6);
+        if rna.len() > pf_max_length {
+            println!(
+                "Skipping base-pair probabilities: longer than {} nt",
+                pf_max_length
+            );
+            continue;
+        }
+        let ensemble = fold::partition(&rna, mfe.dg);
+        // Pairs above one half are compatible, so they form a structure.
+        let mut likely = vec![b'.'; rna.len()];
+        for &(i, j, p) in &ensemble.pairs {
+            if p > 0.5 {
+                likely[i] = b'(';
+                likely[j] = b')';
+            }
+        }
+        println!("{} (pairs with p > 0.5)", String::from_utf8_lossy(&likely));
+        println!(
+            "Ensemble free energy {:.2} kcal/mol, MFE structure frequency {:.4}",
+            ensemble.dg,
+            (-(mfe.dg - ensemble.dg) / energy::KT).exp()
+        );
+        if let Some((file, _)) = bpp.as_mut() {
+            for &(i, j, p) in &ensemble.pairs {
+                writeln!(file, "{}\t{}\t{}\t{:.6}", id, i + 1, j + 1, p)?;
+            }
+        }
+    }
+    if let Some((_, p)) = out {
+        println!("Structures written to {}", p);
+    }
+    if let Some((_, p)) = bpp {
+        println!("Base-pair probabilities written to {}", p);
+    }
+    Ok(())
 }
@@ -0,0 +1,384 @@
+use crate::digest::{End, Fragment, Methylome, compute_fragments, find_cuts_multi};
+use crate::enzyme::{Enzyme, Methylation, revcomp_iupac};
+use std::fs;
+use std::io::Write;
+
+/// A double-stranded piece ready for ligation. `top` runs from the top-strand
+/// cut on the left to the one on the right; `bottom` is the other strand,
+/// 5'→3', between its own cuts. Overhangs are written as top-strand text.
+#[derive(Debug, Clone)]
+pub struct Part {
+    pub name: String,
+    pub top: String,
+    pub bottom: String,
+    pub left: End,
+    pub right: End,
+    pub left_enzyme: Option<String>,
+    pub right_enzyme: Option<String>,
+}
+
+fn signed_overhang(end: &End) -> i64 {
+    match end {
+        End::FivePrime(s) => s.len() as i64,
+        End::ThreePrime(s) => -(s.len() as i64),
+        End::Blunt | End::Terminus => 0,
+    }
+}
+
+fn flip_end(end: &End) -> End {
+    match end {
+        End::FivePrime(s) => End::FivePrime(revcomp_iupac(s)),
+        End::ThreePrime(s) => End::ThreePrime(revcomp_iupac(s)),
+        other => other.clone(),
+    }
+}
+
+fn overhang_text(end: &End) -> Option<&str> {
+    match end {
+        End::FivePrime(s) | End::ThreePrime(s) => Some(s),
+        _ => None,
+    }
+}
+
+impl Part {
+    pub fn from_fragment(name: &str, dna: &[u8], frag: &Fragment) -> Part {
+        let n = dna.len() as i64;
+        let slice = |from: i64, len: i64| -> String {
+            (0..len.max(0))
+                .map(|i| dna[(from + i).rem_euclid(n) as usize] as char)
+                .collect()
+        };
+        let start = frag.start as i64;
+        let left_bottom = start + signed_overhang(&frag.left);
+        let bottom_len =
+            frag.length as i64 - signed_overhang(&frag.left) + signed_overhang(&frag.right);
+        Part {
+            name: name.to_string(),
+            top: slice(start, frag.length as i64),
+            bottom: revcomp_iupac(&slice(left_bottom, bottom_len)),
+            left: frag.left.clone(),
+            right: frag.right.clone(),
+            left_enzyme: frag.left_enzyme.clone(),
+            right_enzyme: frag.right_enzyme.clone(),
+        }
+    }
+
+    /// The same molecule turned end over end.
+    pub fn flipped(&self) -> Part {
+        Part {
+            name: format!("{}(rev)", self.name),
+            top: self.bottom.clone(),
+            bottom: self.top.clone(),
+            left: flip_end(&self.right),
+            right: flip_end(&self.left),
+            left_enzyme: self.right_enzyme.clone(),
+            right_enzyme: self.left_enzyme.clone(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.top.len()
+    }
+}
+
+/// Whether a right end can be ligated to a left end: both blunt, or sticky
+/// ends of the same polarity whose overhangs anneal.
+pub fn compatible(right: &End, left: &End) -> bool {
+    match (right, left) {
+        (End::Blunt, End::Blunt) => true,
+        (End::FivePrime(a), End::FivePrime(b)) | (End::ThreePrime(a), End::ThreePrime(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Why two ends do not ligate, for the report.
+pub fn incompatibility(right: &End, left: &End) -> String {
+    match (right, left) {
+        (End::Terminus, _) | (_, End::Terminus) => "uncut end".to_string(),
+        (End::Blunt, _) | (_, End::Blunt) => "blunt against sticky end".to_string(),
+        (End::FivePrime(_), End::ThreePrime(_)) | (End::ThreePrime(_), End::FivePrime(_)) => {
+            "5' and 3' overhangs".to_string()
+        }
+        _ => format!(
+            "overhangs {} and {} do not anneal",
+            right.describe(),
+            left.describe()
+        ),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Junction {
+    /// 0-based position in the construct of the first top-strand base after the join.
+    pub pos: usize,
+    pub end: End,
+    pub left_part: String,
+    pub right_part: String,
+    pub enzymes: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Construct {
+    pub name: String,
+    pub seq: String,
+    pub junctions: Vec<Junction>,
+}
+
+/// Ligates `parts` in order and closes the circle, or says which join fails.
+pub fn ligate_circular(name: &str, parts: &[Part]) -> Result<Construct, String> {
+    let mut seq = String::new();
+    let mut junctions = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let next = &parts[(i + 1) % parts.len()];
+        if !compatible(&part.right, &next.left) {
+            return Err(format!(
+                "{} → {}: {}",
+                part.name,
+                next.name,
+                incompatibility(&part.right, &next.left)
+            ));
+        }
+        seq.push_str(&part.top);
+        let enzymes = match (&part.right_enzyme, &next.left_enzyme) {
+            (Some(a), Some(b)) if a == b => a.clone(),
+            (Some(a), Some(b)) => format!("{}/{}", a, b),
+            _ => "?".to_string(),
+        };
+        junctions.push(Junction {
+            pos: seq.len() % parts.iter().map(Part::len).sum::<usize>().max(1),
+            end: part.right.clone(),
+            left_part: part.name.clone(),
+            right_part: next.name.clone(),
+            enzymes,
+        });
+    }
+    Ok(Construct {
+        name: name.to_string(),
+        seq,
+        junctions,
+    })
+}
+
+/// The parts left after cutting `dna` grown in `host`; a circular molecule
+/// yields every fragment, a linear one only the fragments cut on both sides.
+pub fn cut_parts(
+    name: &str,
+    dna: &[u8],
+    enzymes: &[&Enzyme],
+    circular: bool,
+    host: &[Methylation],
+) -> Vec<Part> {
+    let methylome = Methylome::new(dna, host, circular);
+    let cuts = find_cuts_multi(dna, enzymes, circular, &methylome);
+    compute_fragments(dna, &cuts, circular)
+        .iter()
+        .filter(|f| f.left != End::Terminus && f.right != End::Terminus)
+        .enumerate()
+        .map(|(i, f)| Part::from_fragment(&format!("{}#{}", name, i + 1), dna, f))
+        .collect()
+}
+
+/// One attempted ligation: a label and the construct or why it fails.
+pub type Ligation = (String, Result<Construct, String>);
+
+/// Classic restriction cloning: the largest vector fragment is the backbone,
+/// the largest insert fragment is ligated into it in both orientations.
+pub fn restriction_clone(
+    vector: (&str, &[u8]),
+    insert: (&str, &[u8]),
+    enzymes: &[&Enzyme],
+    host: &[Methylation],
+) -> Result<Vec<Ligation>, String> {
+    let backbone = cut_parts(vector.0, vector.1, enzymes, true, host)
+        .into_iter()
+        .max_by_key(Part::len)
+        .ok_or(format!("{} is not cut by these enzymes", vector.0))?;
+    let insert_part = cut_parts(insert.0, insert.1, enzymes, false, host)
+        .into_iter()
+        .max_by_key(Part::len)
+        .ok_or(format!("{} is not cut on both sides", insert.0))?;
+
+    let mut results = Vec::new();
+    if compatible(&backbone.right, &backbone.left) {
+        results.push((
+            "vector self-ligation".to_string(),
+            ligate_circular(vector.0, std::slice::from_ref(&backbone)),
+        ));
+    }
+    for (orientation, part) in [
+        ("forward", insert_part.clone()),
+        ("reverse", insert_part.flipped()),
+    ] {
+        let name = format!("{}_{}_{}", vector.0, insert.0, orientation);
+        results.push((
+            format!("insert {}", orientation),
+            ligate_circular(&name, &[backbone.clone(), part]),
+        ));
+    }
+    Ok(results)
+}
+
+fn hamming(a: &str, b: &str) -> usize {
+    if a.len() != b.len() {
+        return a.len().max(b.len());
+    }
+    a.bytes().zip(b.bytes()).filter(|(x, y)| x != y).count()
+}
+
+/// Fidelity of a set of Golden Gate overhangs: palindromes and overhangs that
+/// equal another one (or its reverse complement) are errors, a single
+/// mismatch is a warning because T4 ligase will join such ends at a low rate.
+pub fn overhang_fidelity(overhangs: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    for (i, a) in overhangs.iter().enumerate() {
+        if *a == revcomp_iupac(a) {
+            errors.push(format!("{} is palindromic and can ligate to itself", a));
+        }
+        for b in &overhangs[i + 1..] {
+            let d = hamming(a, b).min(hamming(a, &revcomp_iupac(b)));
+            if d == 0 {
+                errors.push(format!("{} and {} are interchangeable", a, b));
+            } else if d == 1 {
+                warnings.push(format!("{} and {} differ by one base", a, b));
+            }
+        }
+    }
+    (errors, warnings)
+}
+
+/// Golden Gate assembly with one Type IIS enzyme: each part must release
+/// exactly one fragment, and the parts are chained by matching overhangs
+/// from the vector backbone around to its other end.
+pub fn golden_gate(
+    vector: (&str, &[u8]),
+    parts: &[(String, Vec<u8>)],
+    enzyme: &Enzyme,
+    host: &[Methylation],
+) -> Result<(Construct, Vec<String>), String> {
+    let enzymes = [enzyme];
+    let backbone = cut_parts(vector.0, vector.1, &enzymes, true, host)
+        .into_iter()
+        .max_by_key(Part::len)
+        .ok_or(format!("{} has no {} sites", vector.0, enzyme.name))?;
+
+    let mut pool = Vec::new();
+    for (name, dna) in parts {
+        let mut released = cut_parts(name, dna, &enzymes, false, host);
+        match released.len() {
+            0 => return Err(format!("{}: fewer than two {} sites", name, enzyme.name)),
+            1 => pool.push(released.remove(0)),
+            n => {
+                return Err(format!(
+                    "{}: {} releases {} fragments, internal sites must be removed first",
+                    name, enzyme.name, n
+                ));
+            }
+        }
+    }
+
+    let overhangs: Vec<String> = std::iter::once(&backbone)
+        .chain(&pool)
+        .flat_map(|p| [&p.left, &p.right])
+        .filter_map(|e| overhang_text(e).map(str::to_string))
+        .collect();
+    let mut junction_overhangs: Vec<String> = Vec::new();
+    for o in overhangs {
+        let rc = revcomp_iupac(&o);
+        if !junction_overhangs.iter().any(|j| *j == o || *j == rc) {
+            junction_overhangs.push(o);
+        }
+    }
+    let (errors, warnings) = overhang_fidelity(&junction_overhangs);
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+
+    let mut order = vec![backbone.clone()];
+    let mut used = vec![false; pool.len()];
+    for _ in 0..pool.len() {
+        let current = &order[order.len() - 1].right;
+        let mut found: Vec<Part> = Vec::new();
+        for (i, p) in pool.iter().enumerate() {
+            if used[i] {
+                continue;
+            }
+            for candidate in [p.clone(), p.flipped()] {
+                if compatible(current, &candidate.left) {
+                    used[i] = true;
+                    found.push(candidate);
+                }
+            }
+        }
+        match found.len() {
+            0 => {
+                return Err(format!(
+                    "no part continues from {} overhang {}",
+                    order[order.len() - 1].name,
+                    current.describe()
+                ));
+            }
+            1 => order.push(found.remove(0)),
+            _ => {
+                return Err(format!(
+                    "overhang {} is shared by several parts",
+                    current.describe()
+                ));
+            }
+        }
+    }
+
+    let name = format!("{}_golden_gate", vector.0);
+    let construct = ligate_circular(&name, &order)?;
+    Ok((construct, warnings))
+}
+
+pub fn print_junctions(construct: &Construct) {
+    let n = construct.seq.len();
+    let bytes = construct.seq.as_bytes();
+    println!(
+        "{:<6} {:<10} {:<10} {:<24} {:<24} Context",
+        "Idx", "Position", "Overhang", "Left", "Right"
+    );
+    for (i, j) in construct.junctions.iter().enumerate() {
+        let context: String = (0..20)
+            .map(|k| bytes[((j.pos + k) as isize - 10).rem_euclid(n as isize) as usize] as char)
+            .collect();
+        println!(
+            "{:<6} {:<10} {:<10} {:<24} {:<24} {}|{}",
+            i + 1,
+            j.pos + 1,
+            j.end.describe(),
+            j.left_part,
+            j.right_part,
+            &context[..10],
+            &context[10..]
+        );
+    }
+}
+
+/// Circular constructs as FASTA, with each junction in the header as
+/// `position:enzyme:overhang`.
+pub fn write_fasta(path: &str, constructs: &[&Construct]) -> Result<(), String> {
+    let mut file = fs::File::create(path).map_err(|e| format!("cannot write {}: {}", path, e))?;
+    for c in constructs {
+        let junctions: Vec<String> = c
+            .junctions
+            .iter()
+            .map(|j| format!("{}:{}:{}", j.pos + 1, j.enzymes, j.end.describe()))
+            .collect();
+        let mut text = format!(
+            ">{} topology=circular length={} junctions={}\n",
+            c.name,
+            c.seq.len(),
+            junctions.join(",")
+        );
+        for chunk in c.seq.as_bytes().chunks(70) {
+            text.push_str(&String::from_utf8_lossy(chunk));
+            text.push('\n');
+        }
+        file.write_all(text.as_bytes())
+            .map_err(|e| format!("cannot write {}: {}", path, e))?;
+    }
+    Ok(())
+}
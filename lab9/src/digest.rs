@@ -0,0 +1,290 @@
+use crate::enzyme::{Enzyme, Methylation, iupac_match, revcomp_iupac};
+
+/// A double-strand break. `top` and `bottom` are the positions (between
+/// bases, top-strand coordinates) where each strand is cut; `overhang` is
+/// `bottom - top` before any wrap-around on a circular sequence.
+#[derive(Debug, Clone)]
+pub struct Cut {
+    pub enzyme: String,
+    pub forward: bool,
+    pub top: usize,
+    pub bottom: usize,
+    pub overhang: i64,
+}
+
+/// What a fragment end looks like after cutting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum End {
+    Terminus,
+    Blunt,
+    FivePrime(String),
+    ThreePrime(String),
+}
+
+impl End {
+    fn at(dna: &[u8], cut: &Cut) -> End {
+        let n = dna.len();
+        let text = |from: usize, len: usize| -> String {
+            (0..len).map(|i| dna[(from + i) % n] as char).collect()
+        };
+        let len = cut.overhang.unsigned_abs() as usize;
+        if cut.overhang > 0 {
+            End::FivePrime(text(cut.top, len))
+        } else if cut.overhang < 0 {
+            End::ThreePrime(text(cut.bottom, len))
+        } else {
+            End::Blunt
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            End::Terminus => "end".to_string(),
+            End::Blunt => "blunt".to_string(),
+            End::FivePrime(s) => format!("5'{}", s),
+            End::ThreePrime(s) => format!("3'{}", s),
+        }
+    }
+}
+
+/// A fragment from `start` (0-based) to `end` (exclusive). On a circular
+/// sequence the fragment across the origin has `end <= start`.
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    pub start: usize,
+    pub end: usize,
+    pub length: usize,
+    pub left: End,
+    pub right: End,
+    pub left_enzyme: Option<String>,
+    pub right_enzyme: Option<String>,
+}
+
+fn matches_at(dna: &[u8], i: usize, site: &[u8]) -> bool {
+    site.iter()
+        .zip(&dna[i..i + site.len()])
+        .all(|(&code, &base)| iupac_match(code, base))
+}
+
+/// Recognition sites as `(start, forward)`. Non-palindromic sites are also
+/// searched as their reverse complement, i.e. bound on the bottom strand.
+/// On a circular sequence sites spanning the origin are included.
+pub fn find_sites(dna: &[u8], enzyme: &Enzyme, circular: bool) -> Vec<(usize, bool)> {
+    let site = enzyme.site.as_bytes();
+    let n = dna.len();
+    let mut sites = Vec::new();
+    if site.is_empty() || site.len() > n {
+        return sites;
+    }
+    let haystack: Vec<u8> = if circular {
+        dna.iter().chain(&dna[..site.len() - 1]).copied().collect()
+    } else {
+        dna.to_vec()
+    };
+    let rc = revcomp_iupac(&enzyme.site);
+    let both = !enzyme.is_palindromic();
+    for i in 0..=haystack.len() - site.len() {
+        if i >= n {
+            break;
+        }
+        if matches_at(&haystack, i, site) {
+            sites.push((i, true));
+        }
+        if both && matches_at(&haystack, i, rc.as_bytes()) {
+            sites.push((i, false));
+        }
+    }
+    sites
+}
+
+/// Methylated bases of a sequence for each kind of host methylation.
+pub struct Methylome {
+    marks: Vec<(Methylation, Vec<bool>)>,
+}
+
+impl Methylome {
+    pub fn new(dna: &[u8], host: &[Methylation], circular: bool) -> Methylome {
+        let n = dna.len();
+        let marks = host
+            .iter()
+            .map(|&kind| {
+                let motif = Enzyme::new(kind.name(), kind.motif(), 0, 0);
+                let mut marked = vec![false; n];
+                for (start, _) in find_sites(dna, &motif, circular) {
+                    for &offset in kind.marked() {
+                        marked[(start + offset) % n] = true;
+                    }
+                }
+                (kind, marked)
+            })
+            .collect();
+        Methylome { marks }
+    }
+
+    /// The methylation that stops `enzyme` at the site starting at `start`.
+    pub fn blocking(&self, enzyme: &Enzyme, start: usize) -> Option<Methylation> {
+        self.marks
+            .iter()
+            .filter(|(kind, _)| enzyme.blocked_by.contains(kind))
+            .find(|(_, marked)| {
+                let n = marked.len();
+                (start..start + enzyme.site.len()).any(|i| marked[i % n])
+            })
+            .map(|(kind, _)| *kind)
+    }
+}
+
+/// Sites the enzyme cannot cut because of methylation, as `(start, forward, cause)`.
+pub fn blocked_sites(
+    dna: &[u8],
+    enzyme: &Enzyme,
+    circular: bool,
+    methylome: &Methylome,
+) -> Vec<(usize, bool, Methylation)> {
+    find_sites(dna, enzyme, circular)
+        .into_iter()
+        .filter_map(|(start, forward)| {
+            methylome
+                .blocking(enzyme, start)
+                .map(|m| (start, forward, m))
+        })
+        .collect()
+}
+
+/// Recognition sites the enzyme can cut, counted once each however many
+/// cuts it makes there or whether they fall inside a linear sequence.
+pub fn site_count(dna: &[u8], enzyme: &Enzyme, circular: bool, methylome: &Methylome) -> usize {
+    let mut starts: Vec<usize> = find_sites(dna, enzyme, circular)
+        .into_iter()
+        .filter(|&(start, _)| methylome.blocking(enzyme, start).is_none())
+        .map(|(start, _)| start)
+        .collect();
+    starts.dedup();
+    starts.len()
+}
+
+/// Cut positions for every cleavable site, mirrored for sites on the bottom
+/// strand. On a linear sequence cuts that would fall outside it are dropped;
+/// on a circular one they wrap around.
+pub fn find_cuts(dna: &[u8], enzyme: &Enzyme, circular: bool, methylome: &Methylome) -> Vec<Cut> {
+    let n = dna.len() as i64;
+    let len = enzyme.site.len() as i64;
+    let mut cuts = Vec::new();
+
+    for (site, forward) in find_sites(dna, enzyme, circular) {
+        if methylome.blocking(enzyme, site).is_some() {
+            continue;
+        }
+        for &(top, bottom) in &enzyme.cuts {
+            let (t, b) = if forward {
+                (top, bottom)
+            } else {
+                (len - bottom, len - top)
+            };
+            let (t, b) = (site as i64 + t, site as i64 + b);
+            if !circular && (t <= 0 || b <= 0 || t >= n || b >= n) {
+                continue;
+            }
+            cuts.push(Cut {
+                enzyme: enzyme.name.clone(),
+                forward,
+                top: t.rem_euclid(n) as usize,
+                bottom: b.rem_euclid(n) as usize,
+                overhang: b - t,
+            });
+        }
+    }
+
+    cuts.sort_by_key(|c| c.top);
+    cuts.dedup_by_key(|c| c.top);
+    cuts
+}
+
+/// One digest with all `enzymes` together.
+pub fn find_cuts_multi(
+    dna: &[u8],
+    enzymes: &[&Enzyme],
+    circular: bool,
+    methylome: &Methylome,
+) -> Vec<Cut> {
+    let mut cuts: Vec<Cut> = enzymes
+        .iter()
+        .flat_map(|e| find_cuts(dna, e, circular, methylome))
+        .collect();
+    cuts.sort_by_key(|c| c.top);
+    cuts.dedup_by_key(|c| c.top);
+    cuts
+}
+
+/// Fragments between consecutive top-strand cuts, with the end left by each
+/// cut. `cuts` must be sorted by `top`.
+pub fn compute_fragments(dna: &[u8], cuts: &[Cut], circular: bool) -> Vec<Fragment> {
+    let n = dna.len();
+    let mut fragments = Vec::new();
+
+    if circular {
+        if cuts.is_empty() {
+            fragments.push(Fragment {
+                start: 0,
+                end: n,
+                length: n,
+                left: End::Terminus,
+                right: End::Terminus,
+                left_enzyme: None,
+                right_enzyme: None,
+            });
+            return fragments;
+        }
+        for i in 0..cuts.len() {
+            let (a, b) = (&cuts[i], &cuts[(i + 1) % cuts.len()]);
+            let length = if b.top > a.top {
+                b.top - a.top
+            } else {
+                n - a.top + b.top
+            };
+            fragments.push(Fragment {
+                start: a.top,
+                end: b.top,
+                length,
+                left: End::at(dna, a),
+                right: End::at(dna, b),
+                left_enzyme: Some(a.enzyme.clone()),
+                right_enzyme: Some(b.enzyme.clone()),
+            });
+        }
+        return fragments;
+    }
+
+    let mut start = 0;
+    let mut left = End::Terminus;
+    let mut left_enzyme = None;
+    for cut in cuts {
+        if cut.top > start {
+            let end = End::at(dna, cut);
+            fragments.push(Fragment {
+                start,
+                end: cut.top,
+                length: cut.top - start,
+                left,
+                right: end.clone(),
+                left_enzyme,
+                right_enzyme: Some(cut.enzyme.clone()),
+            });
+            left = end;
+            left_enzyme = Some(cut.enzyme.clone());
+            start = cut.top;
+        }
+    }
+    if n > start {
+        fragments.push(Fragment {
+            start,
+            end: n,
+            length: n - start,
+            left,
+            right: End::Terminus,
+            left_enzyme,
+            right_enzyme: None,
+        });
+    }
+    fragments
+}
@@ -0,0 +1,343 @@
+use std::fs;
+
+/// Base methylation done by the host the DNA was grown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Methylation {
+    /// N6-methyladenine in GATC (E. coli Dam).
+    Dam,
+    /// C5-methylcytosine in CCWGG (E. coli Dcm).
+    Dcm,
+    /// C5-methylcytosine in CG (mammals, plants, M.SssI).
+    CpG,
+}
+
+impl Methylation {
+    pub fn motif(&self) -> &'static str {
+        match self {
+            Methylation::Dam => "GATC",
+            Methylation::Dcm => "CCWGG",
+            Methylation::CpG => "CG",
+        }
+    }
+
+    /// Offsets in the motif of the bases methylated on either strand, in
+    /// top-strand coordinates: GATC carries a methyl on the A of both strands.
+    pub fn marked(&self) -> &'static [usize] {
+        match self {
+            Methylation::Dam => &[1, 2],
+            Methylation::Dcm => &[1, 3],
+            Methylation::CpG => &[0, 1],
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Methylation::Dam => "Dam",
+            Methylation::Dcm => "Dcm",
+            Methylation::CpG => "CpG",
+        }
+    }
+}
+
+/// `none`, `ecoli` (Dam and Dcm), `mammalian` (CpG) or a comma-separated
+/// list of `dam`, `dcm` and `cpg`.
+pub fn parse_host(spec: &str) -> Result<Vec<Methylation>, String> {
+    let mut host = Vec::new();
+    for part in spec.split(',').map(|p| p.trim().to_ascii_lowercase()) {
+        let kinds: &[Methylation] = match part.as_str() {
+            "" | "none" => &[],
+            "ecoli" => &[Methylation::Dam, Methylation::Dcm],
+            "mammalian" => &[Methylation::CpG],
+            "dam" => &[Methylation::Dam],
+            "dcm" => &[Methylation::Dcm],
+            "cpg" => &[Methylation::CpG],
+            other => return Err(format!("unknown methylation: {}", other)),
+        };
+        for &k in kinds {
+            if !host.contains(&k) {
+                host.push(k);
+            }
+        }
+    }
+    Ok(host)
+}
+
+/// Methylation that blocks cleavage when it falls inside the recognition
+/// site, after the NEB sensitivity tables. Enzymes not listed are treated
+/// as insensitive.
+fn sensitivity(name: &str) -> Vec<Methylation> {
+    const DAM: &[&str] = &[
+        "AlwI",
+        "BclI",
+        "BsaBI",
+        "BspDI",
+        "BspHI",
+        "ClaI",
+        "DpnII",
+        "HphI",
+        "Hpy188I",
+        "Hpy188III",
+        "MboI",
+        "MboII",
+        "NruI",
+        "TaqI",
+        "XbaI",
+    ];
+    const DCM: &[&str] = &[
+        "Acc65I", "ApaI", "AvaII", "BsaI", "EcoO109I", "EcoRII", "MscI", "PflMI", "PspGI",
+        "Sau96I", "ScrFI", "SexAI", "SfoI", "StuI",
+    ];
+    const CPG: &[&str] = &[
+        "AatII", "AfeI", "AgeI", "AscI", "AsiSI", "AvaI", "BsaI", "BsiWI", "BspDI", "BssHII",
+        "BstUI", "ClaI", "EagI", "FseI", "HhaI", "HinP1I", "HpaII", "KasI", "MluI", "NarI", "NotI",
+        "NruI", "PmlI", "PvuI", "SacII", "SalI", "SfoI", "SmaI", "SnaBI", "XhoI",
+    ];
+    let listed = |table: &[&str]| table.iter().any(|n| n.eq_ignore_ascii_case(name));
+    [
+        (Methylation::Dam, DAM),
+        (Methylation::Dcm, DCM),
+        (Methylation::CpG, CPG),
+    ]
+    .into_iter()
+    .filter(|(_, table)| listed(table))
+    .map(|(m, _)| m)
+    .collect()
+}
+
+/// A restriction enzyme. `site` is the top-strand recognition sequence in
+/// IUPAC code. Each cut is a `(top, bottom)` pair of positions relative to
+/// the first base of the site, both counted on the top strand: EcoRI
+/// (G^AATTC) is `(1, 5)`, BsaI (GGTCTC(1/5)) is `(7, 11)`. `blocked_by`
+/// lists the host methylation the enzyme cannot cut through.
+#[derive(Debug, Clone)]
+pub struct Enzyme {
+    pub name: String,
+    pub site: String,
+    pub cuts: Vec<(i64, i64)>,
+    pub blocked_by: Vec<Methylation>,
+}
+
+impl Enzyme {
+    pub fn new(name: &str, site: &str, top: i64, bottom: i64) -> Enzyme {
+        Enzyme::with_cuts(name, site.to_ascii_uppercase(), vec![(top, bottom)])
+    }
+
+    fn with_cuts(name: &str, site: String, cuts: Vec<(i64, i64)>) -> Enzyme {
+        Enzyme {
+            name: name.to_string(),
+            site,
+            cuts,
+            blocked_by: sensitivity(name),
+        }
+    }
+
+    pub fn is_palindromic(&self) -> bool {
+        revcomp_iupac(&self.site) == self.site
+    }
+
+    /// Recognition site with the cut marks, e.g. `G^AATT_C` (`^` top, `_` bottom).
+    pub fn describe(&self) -> String {
+        match self.cuts.first() {
+            Some(&(top, bottom)) if self.cuts.len() == 1 => {
+                let len = self.site.len() as i64;
+                if (0..=len).contains(&top) && (0..=len).contains(&bottom) {
+                    let mut out = String::new();
+                    for (i, ch) in self.site.chars().enumerate() {
+                        let i = i as i64;
+                        if i == top {
+                            out.push('^');
+                        }
+                        if i == bottom {
+                            out.push('_');
+                        }
+                        out.push(ch);
+                    }
+                    if top == len {
+                        out.push('^');
+                    }
+                    if bottom == len {
+                        out.push('_');
+                    }
+                    out
+                } else {
+                    format!("{}({}/{})", self.site, top - len, bottom - len)
+                }
+            }
+            _ => format!("{} {:?}", self.site, self.cuts),
+        }
+    }
+}
+
+/// Enzymes available without a REBASE file.
+pub fn builtin_enzymes() -> Vec<Enzyme> {
+    vec![
+        Enzyme::new("EcoRI", "GAATTC", 1, 5),
+        Enzyme::new("BamHI", "GGATCC", 1, 5),
+        Enzyme::new("HindIII", "AAGCTT", 1, 5),
+        Enzyme::new("TaqI", "TCGA", 1, 3),
+        Enzyme::new("HaeIII", "GGCC", 2, 2),
+        Enzyme::new("BsaI", "GGTCTC", 7, 11),
+        Enzyme::new("BsmBI", "CGTCTC", 7, 11),
+    ]
+}
+
+/// Bases an IUPAC code stands for.
+pub fn iupac_bases(code: u8) -> &'static [u8] {
+    match code {
+        b'A' => b"A",
+        b'C' => b"C",
+        b'G' => b"G",
+        b'T' | b'U' => b"T",
+        b'R' => b"AG",
+        b'Y' => b"CT",
+        b'S' => b"CG",
+        b'W' => b"AT",
+        b'K' => b"GT",
+        b'M' => b"AC",
+        b'B' => b"CGT",
+        b'D' => b"AGT",
+        b'H' => b"ACT",
+        b'V' => b"ACG",
+        b'N' => b"ACGT",
+        _ => b"",
+    }
+}
+
+pub fn iupac_match(code: u8, base: u8) -> bool {
+    iupac_bases(code).contains(&base)
+}
+
+pub fn revcomp_iupac(site: &str) -> String {
+    site.bytes()
+        .rev()
+        .map(|b| match b {
+            b'A' => 'T',
+            b'T' => 'A',
+            b'C' => 'G',
+            b'G' => 'C',
+            b'R' => 'Y',
+            b'Y' => 'R',
+            b'K' => 'M',
+            b'M' => 'K',
+            b'B' => 'V',
+            b'V' => 'B',
+            b'D' => 'H',
+            b'H' => 'D',
+            other => other as char,
+        })
+        .collect()
+}
+
+/// Loads a REBASE file in either the EMBOSS `withrefm` format (`<1>name`,
+/// `<3>site`) or the `bairoch` format (`ID`/`RS` lines). Enzymes whose cut
+/// positions are unknown are skipped.
+pub fn load_rebase(path: &str) -> Result<Vec<Enzyme>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let enzymes = if text.lines().any(|l| l.starts_with("<1>")) {
+        parse_withrefm(&text)
+    } else {
+        parse_bairoch(&text)
+    };
+    if enzymes.is_empty() {
+        return Err(format!("no usable enzymes in {}", path));
+    }
+    Ok(enzymes)
+}
+
+fn parse_withrefm(text: &str) -> Vec<Enzyme> {
+    let mut enzymes = Vec::new();
+    let mut name: Option<String> = None;
+    for line in text.lines() {
+        if let Some(v) = line.strip_prefix("<1>") {
+            name = Some(v.trim().to_string());
+        } else if let Some(v) = line.strip_prefix("<3>")
+            && let Some(n) = name.take()
+            && let Some((site, cuts)) = parse_withrefm_site(v.trim())
+        {
+            enzymes.push(Enzyme::with_cuts(&n, site, cuts));
+        }
+    }
+    enzymes
+}
+
+/// `GACGT^C`, `GGTCTC(1/5)` or `(8/13)GACNNNNNNTCA(12/7)`; `?` means unknown.
+fn parse_withrefm_site(raw: &str) -> Option<(String, Vec<(i64, i64)>)> {
+    if raw.is_empty() || raw.contains('?') {
+        return None;
+    }
+    let raw = raw.split(',').next()?.to_ascii_uppercase();
+
+    let pair = |s: &str| -> Option<(i64, i64)> {
+        let (a, b) = s.trim_matches(|c| c == '(' || c == ')').split_once('/')?;
+        Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+    };
+
+    let (before, rest) = if raw.starts_with('(') {
+        let close = raw.find(')')?;
+        (Some(pair(&raw[..=close])?), &raw[close + 1..])
+    } else {
+        (None, raw.as_str())
+    };
+    let (core, after) = match rest.find('(') {
+        Some(open) => (&rest[..open], Some(pair(&rest[open..])?)),
+        None => (rest, None),
+    };
+
+    let site: String = core.chars().filter(|c| c.is_ascii_alphabetic()).collect();
+    let len = site.len() as i64;
+    let mut cuts = Vec::new();
+    if let Some((a, b)) = before {
+        cuts.push((-a, -b));
+    }
+    if let Some(caret) = core.find('^') {
+        let top = caret as i64;
+        cuts.push((top, len - top));
+    }
+    if let Some((a, b)) = after {
+        cuts.push((len + a, len + b));
+    }
+    if site.is_empty() || cuts.is_empty() {
+        return None;
+    }
+    Some((site, cuts))
+}
+
+fn parse_bairoch(text: &str) -> Vec<Enzyme> {
+    let mut enzymes = Vec::new();
+    let mut name = String::new();
+    let mut rs: Vec<(String, Option<i64>)> = Vec::new();
+
+    let mut finish = |name: &str, rs: &mut Vec<(String, Option<i64>)>| {
+        if let Some((site, Some(cut))) = rs.first().cloned() {
+            let len = site.len() as i64;
+            let bottom = match rs.get(1) {
+                Some((_, Some(c2))) => Some(len - c2),
+                Some((_, None)) => None,
+                None => Some(len - cut),
+            };
+            if let Some(bottom) = bottom {
+                enzymes.push(Enzyme::with_cuts(name, site, vec![(cut, bottom)]));
+            }
+        }
+        rs.clear();
+    };
+
+    for line in text.lines() {
+        if let Some(v) = line.strip_prefix("ID") {
+            name = v.trim().to_string();
+        } else if let Some(v) = line.strip_prefix("RS") {
+            for entry in v.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+                if let Some((site, cut)) = entry.split_once(',') {
+                    rs.push((site.trim().to_ascii_uppercase(), cut.trim().parse().ok()));
+                }
+            }
+        } else if line.starts_with("//") {
+            finish(&name, &mut rs);
+            name.clear();
+        }
+    }
+    if !name.is_empty() {
+        finish(&name, &mut rs);
+    }
+    enzymes
+}
@@ -1,3 +1,20 @@
+mod cloning;
+mod digest;
+mod enzyme;
+mod map;
+
+use cloning::{golden_gate, print_junctions, restriction_clone, write_fasta};
+use digest::{
+    Cut, End, Fragment, Methylome, blocked_sites, compute_fragments, find_cuts, find_cuts_multi,
+    site_count,
+};
+use enzyme::{Enzyme, Methylation, builtin_enzymes, load_rebase, parse_host};
+use map::{MapSite, draw_map};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+
 fn read_dna_from_stdin() -> Vec<u8> {
     let mut input = String::new();
     io::stdin()
@@ -20,50 +37,6 @@ fn read_dna_from_stdin() -> Vec<u8> {
     seq.into_bytes()
 }
 
-fn find_cuts(dna: &[u8], enzyme: &Enzyme) -> Vec<usize> {
-    let site_bytes = enzyme.site.as_bytes();
-    let site_len = site_bytes.len();
-    let mut cuts = Vec::new();
-
-    if site_len == 0 || site_len > dna.len() {
-        return cuts;
-    }
-
-    let mut i = 0;
-    while i + site_len <= dna.len() {
-        if &dna[i..i + site_len] == site_bytes {
-            let cut_pos = i + enzyme.cut_offset;
-            cuts.push(cut_pos);
-        }
-        i += 1;
-    }
-
-    cuts.sort_unstable();
-    cuts.dedup();
-    cuts
-}
-
-fn compute_fragments(seq_len: usize, cuts: &[usize]) -> Vec<usize> {
-    let mut boundaries = Vec::new();
-    boundaries.push(0);
-    boundaries.extend_from_slice(cuts);
-    boundaries.push(seq_len);
-
-    boundaries.sort_unstable();
-    boundaries.dedup();
-
-    let mut fragments = Vec::new();
-    for w in boundaries.windows(2) {
-        let start = w[0];
-        let end = w[1];
-        if end > start {
-            fragments.push(end - start);
-        }
-    }
-
-    fragments
-}
-
 fn simulate_gel(results: &[(String, Vec<usize>)]) {
     if results.is_empty() {
         println!("Nu există rezultate pentru gel.");
@@ -77,8 +50,12 @@ fn simulate_gel(results: &[(String, Vec<usize>)]) {
 
     for (_, frags) in results {
         for &f in frags {
-            if f < min_len { min_len = f; }
-            if f > max_len { max_len = f; }
+            if f < min_len {
+                min_len = f;
+            }
+            if f > max_len {
+                max_len = f;
+            }
         }
     }
 
@@ -87,9 +64,8 @@ fn simulate_gel(results: &[(String, Vec<usize>)]) {
     let mut matrix = vec![vec![' '; lane_width * results.len()]; largest_lane_height];
 
     for (lane_index, (_, frags)) in results.iter().enumerate() {
-
         for &f in frags {
-            let relative = (f - min_len) as f64 / (max_len - min_len) as f64;
+            let relative = (f - min_len) as f64 / (max_len - min_len).max(1) as f64;
             let y = (relative * (largest_lane_height as f64 - 2.0)) as usize;
 
             let row = largest_lane_height - 2 - y;
@@ -123,67 +99,381 @@ fn simulate_gel(results: &[(String, Vec<usize>)]) {
 
     for (i, (name, _)) in results.iter().enumerate() {
         let pad = lane_width * i + 2;
-        for _ in 0..pad { print!(" "); }
+        for _ in 0..pad {
+            print!(" ");
+        }
         println!("{}", name);
     }
 
     println!();
 }
 
+fn arg_value(args: &[String], name: &str) -> Option<String> {
+    let eq = format!("{}=", name);
+    let mut it = args.iter().skip(1);
+    while let Some(tok) = it.next() {
+        if tok == name {
+            return it.next().cloned();
+        } else if let Some(v) = tok.strip_prefix(&eq) {
+            return Some(v.to_string());
+        }
+    }
+    None
+}
 
-use std::env;
-use std::fs;
-use std::io::{self, Read};
+/// Arguments that are neither flags nor flag values. `--circular` is the
+/// only flag without a value.
+fn positionals(args: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut it = args.iter().skip(1);
+    while let Some(tok) = it.next() {
+        if tok.starts_with("--") {
+            if !tok.contains('=') && tok != "--circular" {
+                it.next();
+            }
+        } else {
+            out.push(tok.clone());
+        }
+    }
+    out
+}
 
-#[derive(Debug)]
-struct Enzyme {
-    name: &'static str,
-    site: &'static str,
-    cut_offset: usize,
+fn fasta_path(args: &[String]) -> Option<String> {
+    positionals(args).into_iter().next()
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+fn read_named_fasta(path: &str) -> Result<(String, Vec<u8>), String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let name = std::path::Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    Ok((name, parse_fasta(&content)))
+}
 
-    let fasta_path = &args[1];
+fn arg_flag(args: &[String], name: &str) -> bool {
+    args.iter().skip(1).any(|a| a == name)
+}
 
-    let fasta_content = fs::read_to_string(fasta_path)
-        .expect("cannot read FASTA file");
+fn find_enzyme<'a>(all: &'a [Enzyme], name: &str) -> Result<&'a Enzyme, String> {
+    all.iter()
+        .find(|e| e.name.eq_ignore_ascii_case(name.trim()))
+        .ok_or(format!("unknown enzyme: {}", name.trim()))
+}
 
-    let dna = parse_fasta(&fasta_content);
-    let len = dna.len();
+/// Enzymes from `--rebase` (or the built-in set), narrowed by `--enzymes`.
+fn select_enzymes<'a>(args: &[String], all: &'a [Enzyme]) -> Result<Vec<&'a Enzyme>, String> {
+    match arg_value(args, "--enzymes") {
+        Some(list) => list
+            .split(',')
+            .filter(|n| !n.trim().is_empty())
+            .map(|n| find_enzyme(all, n))
+            .collect(),
+        None => Ok(all.iter().collect()),
+    }
+}
 
-    println!("Sequence length: {} nucleotides\n", len);
+/// `--digest EcoRI+BamHI,HindIII` gives one digest per comma-separated
+/// group, with every enzyme of a group acting together.
+fn parse_digests<'a>(list: &str, all: &'a [Enzyme]) -> Result<Vec<Vec<&'a Enzyme>>, String> {
+    list.split(',')
+        .filter(|g| !g.trim().is_empty())
+        .map(|g| g.split('+').map(|n| find_enzyme(all, n)).collect())
+        .collect()
+}
 
-    let enzymes = vec![
-        Enzyme { name: "EcoRI",  site: "GAATTC", cut_offset: 1 },
-        Enzyme { name: "BamHI",  site: "GGATCC", cut_offset: 1 },
-        Enzyme { name: "HindIII",site: "AAGCTT", cut_offset: 1 },
-        Enzyme { name: "TaqI",   site: "TCGA",   cut_offset: 1 },
-        Enzyme { name: "HaeIII", site: "GGCC",   cut_offset: 2 },
-    ];
+/// Host methylation from `--host` (none by default).
+fn arg_host(args: &[String]) -> Result<Vec<Methylation>, String> {
+    parse_host(&arg_value(args, "--host").unwrap_or_default())
+}
 
+fn print_digest(
+    dna: &[u8],
+    label: &str,
+    site: &str,
+    cuts: &[Cut],
+    blocked: &[(String, usize, Methylation)],
+    fragments: &[Fragment],
+) {
+    println!("=== {} ===", label);
+    println!("Site: {}", site);
+    println!("Cuts: {}", cuts.len());
+    let positions: Vec<String> = cuts
+        .iter()
+        .map(|c| format!("{}{}", c.top + 1, if c.forward { "" } else { "(-)" }))
+        .collect();
+    println!("Positions: [{}]", positions.join(", "));
+    if !blocked.is_empty() {
+        let sites: Vec<String> = blocked
+            .iter()
+            .map(|(name, start, m)| match label.contains('+') {
+                true => format!("{} {} ({})", name, start + 1, m.name()),
+                false => format!("{} ({})", start + 1, m.name()),
+            })
+            .collect();
+        println!("Blocked by methylation: [{}]", sites.join(", "));
+    }
+    println!(
+        "{:<8} {:<8} {:<8} {:<16} {:<16}",
+        "Start", "End", "Length", "Left", "Right"
+    );
+    for f in fragments {
+        let end = |e: &End, enzyme: &Option<String>| match enzyme {
+            Some(name) if label.contains('+') => format!("{} {}", name, e.describe()),
+            _ => e.describe(),
+        };
+        println!(
+            "{:<8} {:<8} {:<8} {:<16} {:<16}",
+            f.start + 1,
+            if f.end == 0 { dna.len() } else { f.end },
+            f.length,
+            end(&f.left, &f.left_enzyme),
+            end(&f.right, &f.right_enzyme)
+        );
+    }
+    println!();
+}
+
+/// `lab9 clone --vector V --insert I --enzymes A[,B]`: cuts both, ligates
+/// the insert into the vector backbone in each orientation.
+fn run_clone(args: &[String], all: &[Enzyme]) -> Result<(), Box<dyn std::error::Error>> {
+    let vector = read_named_fasta(&arg_value(args, "--vector").ok_or("--vector is required")?)?;
+    let insert = read_named_fasta(&arg_value(args, "--insert").ok_or("--insert is required")?)?;
+    if arg_value(args, "--enzymes").is_none() {
+        return Err("--enzymes is required".into());
+    }
+    let enzymes = select_enzymes(args, all)?;
+    let out = arg_value(args, "--out").unwrap_or_else(|| "construct.fasta".to_string());
+
+    let results = restriction_clone(
+        (&vector.0, &vector.1),
+        (&insert.0, &insert.1),
+        &enzymes,
+        &arg_host(args)?,
+    )?;
+    let mut constructs = Vec::new();
+    for (label, result) in &results {
+        match result {
+            Ok(c) => {
+                println!("=== {}: {} bp ===", label, c.seq.len());
+                print_junctions(c);
+                println!();
+                if !label.starts_with("vector") {
+                    constructs.push(c);
+                }
+            }
+            Err(e) => println!("=== {}: no ligation ({}) ===\n", label, e),
+        }
+    }
+    if results
+        .iter()
+        .any(|(l, r)| l.starts_with("vector") && r.is_ok())
+    {
+        println!("Warning: the vector ends are compatible and can re-ligate without insert.");
+    }
+    if constructs.is_empty() {
+        return Err("no viable construct".into());
+    }
+    write_fasta(&out, &constructs)?;
+    println!("Constructs written to {}", out);
+    Ok(())
+}
+
+/// `lab9 goldengate --vector V --enzyme BsaI part1.fa part2.fa ...`
+fn run_golden_gate(args: &[String], all: &[Enzyme]) -> Result<(), Box<dyn std::error::Error>> {
+    let vector = read_named_fasta(&arg_value(args, "--vector").ok_or("--vector is required")?)?;
+    let enzyme = find_enzyme(
+        all,
+        &arg_value(args, "--enzyme").unwrap_or_else(|| "BsaI".to_string()),
+    )?;
+    let parts = positionals(args)
+        .iter()
+        .map(|p| read_named_fasta(p))
+        .collect::<Result<Vec<_>, _>>()?;
+    if parts.is_empty() {
+        return Err("no parts given".into());
+    }
+    let out = arg_value(args, "--out").unwrap_or_else(|| "construct.fasta".to_string());
+
+    let (construct, warnings) =
+        golden_gate((&vector.0, &vector.1), &parts, enzyme, &arg_host(args)?)?;
+    println!(
+        "=== Golden Gate with {}: {} parts, {} bp ===",
+        enzyme.name,
+        parts.len(),
+        construct.seq.len()
+    );
+    print_junctions(&construct);
+    for w in &warnings {
+        println!("Warning: {}", w);
+    }
+    write_fasta(&out, &[&construct])?;
+    println!("\nConstruct written to {}", out);
+    Ok(())
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+    if let Some(command) = args.get(1).filter(|c| *c == "clone" || *c == "goldengate") {
+        let all = match arg_value(&args, "--rebase") {
+            Some(path) => load_rebase(&path)?,
+            None => builtin_enzymes(),
+        };
+        return if command == "clone" {
+            run_clone(&args[1..], &all)
+        } else {
+            run_golden_gate(&args[1..], &all)
+        };
+    }
+
+    let dna = match fasta_path(&args).filter(|p| p != "-") {
+        Some(path) => {
+            let fasta_content = fs::read_to_string(path).expect("cannot read FASTA file");
+            parse_fasta(&fasta_content)
+        }
+        None => read_dna_from_stdin(),
+    };
+    let len = dna.len();
+    let circular = arg_flag(&args, "--circular");
+    let host = arg_host(&args)?;
+    let methylome = Methylome::new(&dna, &host, circular);
+
+    println!(
+        "Sequence length: {} nucleotides ({})",
+        len,
+        if circular { "circular" } else { "linear" }
+    );
+    let host_names: Vec<&str> = host.iter().map(|m| m.name()).collect();
+    println!(
+        "Host methylation: {}\n",
+        if host.is_empty() {
+            "none".to_string()
+        } else {
+            host_names.join(", ")
+        }
+    );
+
+    let all = match arg_value(&args, "--rebase") {
+        Some(path) => load_rebase(&path)?,
+        None => builtin_enzymes(),
+    };
+    let enzymes = select_enzymes(&args, &all)?;
+    let digests: Vec<Vec<&Enzyme>> = match arg_value(&args, "--digest") {
+        Some(list) => parse_digests(&list, &all)?,
+        None => enzymes.iter().map(|&e| vec![e]).collect(),
+    };
+
+    // A whole REBASE file is mostly non-cutters; only list the ones that cut.
+    let whole_database =
+        arg_value(&args, "--rebase").is_some() && arg_value(&args, "--enzymes").is_none();
     let mut all_results = Vec::new();
 
-    for enzyme in &enzymes {
-        let cuts = find_cuts(&dna, enzyme);
-        let fragments = compute_fragments(len, &cuts);
-        println!("=== {} ===", enzyme.name);
-        println!("Site: {}", enzyme.site);
-        println!("Cuts: {}", cuts.len());
-        println!("Positions: {:?}", cuts.iter().map(|x| x + 1).collect::<Vec<_>>());
-        println!("Fragments: {:?}\n", fragments);
-        all_results.push((enzyme.name.to_string(), fragments));
+    for group in &digests {
+        let cuts = find_cuts_multi(&dna, group, circular, &methylome);
+        let blocked: Vec<(String, usize, Methylation)> = group
+            .iter()
+            .flat_map(|e| {
+                blocked_sites(&dna, e, circular, &methylome)
+                    .into_iter()
+                    .map(|(start, _, m)| (e.name.clone(), start, m))
+            })
+            .collect();
+        if cuts.is_empty() && blocked.is_empty() && whole_database && group.len() == 1 {
+            continue;
+        }
+        let fragments = compute_fragments(&dna, &cuts, circular);
+        let label = group
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect::<Vec<_>>()
+            .join("+");
+        let sites = group
+            .iter()
+            .map(|e| e.describe())
+            .collect::<Vec<_>>()
+            .join(", ");
+        print_digest(&dna, &label, &sites, &cuts, &blocked, &fragments);
+        all_results.push((
+            label,
+            fragments.iter().map(|f| f.length).collect::<Vec<_>>(),
+        ));
+    }
+
+    // Classed by recognition sites, not cuts: BaeI and the like cut twice at each.
+    let counts: Vec<(&Enzyme, usize)> = enzymes
+        .iter()
+        .map(|&e| (e, site_count(&dna, e, circular, &methylome)))
+        .collect();
+    let cutters = |n: usize| -> Vec<&str> {
+        counts
+            .iter()
+            .filter(|(_, c)| *c == n)
+            .map(|(e, _)| e.name.as_str())
+            .collect()
+    };
+    println!("Single cutters: {}", cutters(1).join(", "));
+    println!("Double cutters: {}", cutters(2).join(", "));
+    if !host.is_empty() {
+        let blocked: Vec<String> = enzymes
+            .iter()
+            .filter_map(|e| {
+                let sites = blocked_sites(&dna, e, circular, &methylome);
+                (!sites.is_empty()).then(|| format!("{} ({})", e.name, sites.len()))
+            })
+            .collect();
+        println!("Sites lost to methylation: {}", blocked.join(", "));
+    }
+    println!();
+
+    if let Some(path) = arg_value(&args, "--map") {
+        // Map the enzymes of --digest if given, otherwise the single and double cutters.
+        let mapped: Vec<&Enzyme> = match arg_value(&args, "--digest") {
+            Some(_) => {
+                let mut seen = HashSet::new();
+                digests
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .filter(|e| seen.insert(e.name.as_str()))
+                    .collect()
+            }
+            None => counts
+                .iter()
+                .filter(|(_, c)| (1..=2).contains(c))
+                .map(|(e, _)| *e)
+                .collect(),
+        };
+        let sites: Vec<MapSite> = mapped
+            .iter()
+            .flat_map(|e| find_cuts(&dna, e, circular, &methylome))
+            .map(|c| MapSite {
+                name: c.enzyme,
+                pos: c.top,
+            })
+            .collect();
+        let title = fasta_path(&args).unwrap_or_else(|| "sequence".to_string());
+        draw_map(&path, &title, len, &sites, circular)?;
+        println!("Restriction map written to {}", path);
     }
 
     println!("=== Simulate gel ===\n");
     simulate_gel(&all_results);
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
 
 fn parse_fasta(content: &str) -> Vec<u8> {
     let mut seq = Vec::new();
     for line in content.lines() {
-        if line.starts_with('>') { continue; }
+        if line.starts_with('>') {
+            continue;
+        }
         for ch in line.chars() {
             let up = ch.to_ascii_uppercase();
             if matches!(up, 'A' | 'C' | 'G' | 'T') {
@@ -0,0 +1,179 @@
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+use std::f64::consts::PI;
+
+/// A labelled position on the map (0-based cut position on the top strand).
+#[derive(Debug, Clone)]
+pub struct MapSite {
+    pub name: String,
+    pub pos: usize,
+}
+
+/// Draws a restriction map to PNG or SVG, chosen by the file extension.
+pub fn draw_map(
+    path: &str,
+    title: &str,
+    seq_len: usize,
+    sites: &[MapSite],
+    circular: bool,
+) -> Result<(), Box<dyn Error>> {
+    let size = if circular { (900, 900) } else { (1200, 500) };
+    if path.to_ascii_lowercase().ends_with(".svg") {
+        let root = SVGBackend::new(path, size).into_drawing_area();
+        paint(&root, title, seq_len, sites, circular)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(path, size).into_drawing_area();
+        paint(&root, title, seq_len, sites, circular)?;
+        root.present()?;
+    }
+    Ok(())
+}
+
+fn paint<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    title: &str,
+    seq_len: usize,
+    sites: &[MapSite],
+    circular: bool,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    root.draw(&Text::new(
+        format!("{} ({} bp)", title, seq_len),
+        (20, 15),
+        ("sans-serif", 22).into_font(),
+    ))?;
+
+    let mut sites = sites.to_vec();
+    sites.sort_by_key(|s| s.pos);
+    if circular {
+        paint_circular(root, seq_len, &sites)
+    } else {
+        paint_linear(root, seq_len, &sites)
+    }
+}
+
+/// A round tick spacing giving roughly ten ticks over `len`.
+fn tick_step(len: usize) -> usize {
+    let raw = (len / 10).max(1) as f64;
+    let mag = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * mag)
+        .find(|s| *s >= raw)
+        .unwrap_or(10.0 * mag);
+    step as usize
+}
+
+fn paint_linear<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    seq_len: usize,
+    sites: &[MapSite],
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let (w, h) = root.dim_in_pixel();
+    let (x0, x1) = (60i32, w as i32 - 60);
+    let y = h as i32 * 2 / 3;
+    let x_of = |pos: usize| x0 + ((x1 - x0) as f64 * pos as f64 / seq_len.max(1) as f64) as i32;
+    let font = ("sans-serif", 13).into_font();
+
+    root.draw(&Rectangle::new([(x0, y - 3), (x1, y + 3)], BLACK.filled()))?;
+    let step = tick_step(seq_len);
+    for pos in (0..=seq_len).step_by(step) {
+        let x = x_of(pos);
+        root.draw(&PathElement::new(vec![(x, y + 3), (x, y + 10)], BLACK))?;
+        root.draw(&Text::new(
+            format!("{}", pos),
+            (x - 10, y + 14),
+            font.clone(),
+        ))?;
+    }
+
+    // Labels climb in levels so neighbouring sites do not overwrite each other.
+    let mut level_end: Vec<i32> = Vec::new();
+    for site in sites {
+        let x = x_of(site.pos);
+        let label = format!("{} ({})", site.name, site.pos + 1);
+        let width = label.len() as i32 * 7;
+        let level = match level_end.iter().position(|&end| end < x) {
+            Some(l) => l,
+            None => {
+                level_end.push(i32::MIN);
+                level_end.len() - 1
+            }
+        };
+        level_end[level] = x + width + 6;
+        let ly = y - 25 - level as i32 * 18;
+        root.draw(&PathElement::new(vec![(x, y - 3), (x, ly + 8)], BLUE))?;
+        root.draw(&Text::new(label, (x + 2, ly - 6), font.clone()))?;
+    }
+    Ok(())
+}
+
+fn paint_circular<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    seq_len: usize,
+    sites: &[MapSite],
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let (w, h) = root.dim_in_pixel();
+    let (cx, cy) = (w as f64 / 2.0, h as f64 / 2.0 + 15.0);
+    let r = w.min(h) as f64 * 0.3;
+    let font = ("sans-serif", 13).into_font();
+    // Angle 0 at 12 o'clock, increasing clockwise.
+    let angle = |pos: usize| 2.0 * PI * pos as f64 / seq_len.max(1) as f64;
+    let point = |a: f64, radius: f64| {
+        (
+            (cx + radius * a.sin()).round() as i32,
+            (cy - radius * a.cos()).round() as i32,
+        )
+    };
+
+    let ring: Vec<(i32, i32)> = (0..=360).map(|d| point(d as f64 * PI / 180.0, r)).collect();
+    root.draw(&PathElement::new(ring, BLACK.stroke_width(3)))?;
+    let step = tick_step(seq_len);
+    for pos in (0..seq_len).step_by(step) {
+        let a = angle(pos);
+        root.draw(&PathElement::new(
+            vec![point(a, r - 8.0), point(a, r)],
+            BLACK,
+        ))?;
+        let (tx, ty) = point(a, r - 28.0);
+        root.draw(&Text::new(
+            format!("{}", pos),
+            (tx - 12, ty - 6),
+            font.clone(),
+        ))?;
+    }
+
+    // Spread labels to at least `min_gap` radians apart, keeping their order.
+    let min_gap = 0.09;
+    let mut label_angles: Vec<f64> = sites.iter().map(|s| angle(s.pos)).collect();
+    for i in 1..label_angles.len() {
+        if label_angles[i] < label_angles[i - 1] + min_gap {
+            label_angles[i] = label_angles[i - 1] + min_gap;
+        }
+    }
+
+    for (site, &la) in sites.iter().zip(&label_angles) {
+        let a = angle(site.pos);
+        let label = format!("{} ({})", site.name, site.pos + 1);
+        root.draw(&PathElement::new(
+            vec![point(a, r), point(a, r + 15.0), point(la, r + 40.0)],
+            BLUE,
+        ))?;
+        let (lx, ly) = point(la, r + 45.0);
+        let width = label.len() as i32 * 7;
+        let lx = if la.sin() < 0.0 { lx - width } else { lx };
+        root.draw(&Text::new(label, (lx, ly - 7), font.clone()))?;
+    }
+    Ok(())
+}